@@ -1,61 +1,1412 @@
-use crate::vertex::Vertex;
+use crate::math::{add, cross, dot, lerp, norm, scale, sub};
+use crate::vertex::{Vertex, Vertex3};
+use std::fmt::Debug;
 
-use std::convert::TryInto;
-use std::sync::Mutex;
+pub use crate::math::Coordinates;
 
-use lazy_static::lazy_static;
+type Point = [f64; 2];
 
+fn to_point(vertex: &Vertex) -> Point {
+    [vertex.position[0] as f64, vertex.position[1] as f64]
+}
+
+fn to_vertex(point: Point) -> Vertex {
+    Vertex::new(point[0] as f32, point[1] as f32)
+}
+
+// Anything a curve can be made of, like the vertices on the screen or the
+// points of a camera path in space
+pub trait ControlPoint: Copy + Debug {
+    type Coordinates: Coordinates;
+
+    fn coordinates(&self) -> Self::Coordinates;
+    fn from_coordinates(coordinates: Self::Coordinates) -> Self;
+}
+
+impl ControlPoint for Vertex {
+    type Coordinates = Point;
+
+    fn coordinates(&self) -> Point {
+        to_point(self)
+    }
+
+    fn from_coordinates(coordinates: Point) -> Self {
+        to_vertex(coordinates)
+    }
+}
+
+impl ControlPoint for Vertex3 {
+    type Coordinates = [f64; 3];
+
+    fn coordinates(&self) -> [f64; 3] {
+        let [x, y, z] = self.position;
+        [x as f64, y as f64, z as f64]
+    }
+
+    fn from_coordinates([x, y, z]: [f64; 3]) -> Self {
+        Vertex3::new(x as f32, y as f32, z as f32)
+    }
+}
+
+// Curves kept in f64 all the way, like the ones being fitted to samples
+impl<const N: usize> ControlPoint for [f64; N] {
+    type Coordinates = Self;
+
+    fn coordinates(&self) -> Self {
+        *self
+    }
+
+    fn from_coordinates(coordinates: Self) -> Self {
+        coordinates
+    }
+}
+
+// De Casteljau's algorithm, only convex combinations of the control points
+// are taken so it stays stable for any degree. `scratch` is overwritten.
+fn de_casteljau<P: Coordinates>(scratch: &mut Vec<P>, points: &[P], t: f64) -> P {
+    scratch.clear();
+    scratch.extend_from_slice(points);
+
+    let n = scratch.len();
+    for level in 1..n {
+        for i in 0..n - level {
+            scratch[i] = lerp(scratch[i], scratch[i + 1], t);
+        }
+    }
+
+    scratch[0]
+}
+
+// Control polygons of the two halves of the curve split at `t`
+fn split_points<P: Coordinates>(points: &[P], t: f64) -> (Vec<P>, Vec<P>) {
+    let n = points.len();
+    let mut level = points.to_vec();
+    let mut left = Vec::with_capacity(n);
+    let mut right = Vec::with_capacity(n);
+
+    for _ in 0..n {
+        left.push(level[0]);
+        right.push(level[level.len() - 1]);
+        level = level.windows(2).map(|w| lerp(w[0], w[1], t)).collect();
+    }
+
+    right.reverse();
+    (left, right)
+}
+
+// Control polygon of the part between `t0` and `t1`, reversed if `t0` > `t1`
+fn segment_points<P: Coordinates>(points: &[P], t0: f64, t1: f64) -> Vec<P> {
+    let (lo, hi) = (t0.min(t1), t0.max(t1));
+
+    let (_, tail) = split_points(points, lo);
+    let u = if lo == 1. { 0. } else { (hi - lo) / (1. - lo) };
+    let (mut middle, _) = split_points(&tail, u);
+
+    if t0 > t1 {
+        middle.reverse();
+    }
+    middle
+}
+
+// Largest distance from the inner control points to the chord. The curve lies
+// inside the control polygon's hull, so it never strays further than this.
+fn flatness<P: Coordinates>(points: &[P]) -> f64 {
+    if points.len() < 3 {
+        return 0.;
+    }
+
+    let (a, b) = (points[0], points[points.len() - 1]);
+    let chord = sub(b, a);
+    let length = dot(chord, chord);
+
+    points[1..points.len() - 1]
+        .iter()
+        .map(|&p| {
+            let offset = sub(p, a);
+            if length == 0. {
+                norm(offset)
+            } else {
+                norm(sub(offset, scale(chord, dot(offset, chord) / length)))
+            }
+        })
+        .fold(0., f64::max)
+}
+
+// `project` maps the control points to the space of the curve, which is the
+// identity for polynomial curves and the division by the weight for rational
+// ones
+fn flatten_into<P: Coordinates, Q: Coordinates>(
+    points: &[P],
+    project: fn(P) -> Q,
+    tolerance: f64,
+    depth: usize,
+    out: &mut Vec<Q>,
+) {
+    let projected: Vec<Q> = points.iter().copied().map(project).collect();
+    if depth == 0 || flatness(&projected) <= tolerance {
+        out.push(projected[projected.len() - 1]);
+        return;
+    }
+
+    let (left, right) = split_points(points, 0.5);
+    flatten_into(&left, project, tolerance, depth - 1, out);
+    flatten_into(&right, project, tolerance, depth - 1, out);
+}
+
+// Control points of the derivative, a curve one degree lower
+fn hodograph<P: Coordinates>(points: &[P]) -> Vec<P> {
+    let n = points.len().saturating_sub(1) as f64;
+    points
+        .windows(2)
+        .map(|w| scale(sub(w[1], w[0]), n))
+        .collect()
+}
+
+// Unit direction of the curve at `t` from its derivative, or from the next
+// derivative that doesn't vanish there. Zero if none does.
+fn unit_tangent<P: Coordinates>(scratch: &mut Vec<P>, derivative: &[P], t: f64) -> P {
+    let mut points = derivative.to_vec();
+    while !points.is_empty() {
+        let direction = de_casteljau(scratch, &points, t);
+        let length = norm(direction);
+        if length > 0. {
+            return scale(direction, 1. / length);
+        }
+        points = hodograph(&points);
+    }
+    P::ZERO
+}
+
+// Bernstein coefficients of the product of two polynomials given in the
+// Bernstein basis, using b(i, m) * b(j, k) = C(m, i) C(k, j) / C(m + k, i + j)
+// * b(i + j, m + k). Binomials go through logarithms so high degrees don't
+// overflow.
+fn bernstein_product(f: &[f64], g: &[f64]) -> Vec<f64> {
+    if f.is_empty() || g.is_empty() {
+        return Vec::new();
+    }
+
+    let (m, k) = (f.len() - 1, g.len() - 1);
+    let ratio = product_ratios(m, k);
+
+    let mut product = vec![0.; m + k + 1];
+    for (i, a) in f.iter().enumerate() {
+        for (j, b) in g.iter().enumerate() {
+            product[i + j] += ratio(i, j) * a * b;
+        }
+    }
+    product
+}
+
+// C(m, i) C(k, j) / C(m + k, i + j) for the products of Bernstein polynomials
+fn product_ratios(m: usize, k: usize) -> impl Fn(usize, usize) -> f64 {
+    let mut ln_fact = vec![0.; m + k + 1];
+    for i in 1..=m + k {
+        ln_fact[i] = ln_fact[i - 1] + (i as f64).ln();
+    }
+
+    move |i, j| {
+        let ln_binomial = |n: usize, i: usize| ln_fact[n] - ln_fact[i] - ln_fact[n - i];
+        (ln_binomial(m, i) + ln_binomial(k, j) - ln_binomial(m + k, i + j)).exp()
+    }
+}
+
+// The same curve with one more control point
+fn elevate_points<P: Coordinates>(points: &[P]) -> Vec<P> {
+    let n = points.len();
+    let mut elevated = Vec::with_capacity(n + 1);
+    elevated.extend(points.first());
+
+    for i in 1..n {
+        let alpha = i as f64 / n as f64;
+        elevated.push(lerp(points[i], points[i - 1], alpha));
+    }
+
+    elevated.extend(points.last());
+    elevated
+}
+
+// Curve of the given degree closest to the original in the least squares
+// sense over the whole parameter range, keeping both endpoints. The Gram
+// matrices of the Bernstein bases have closed forms, leaving a small linear
+// system for the inner control points. A single point can't keep both ends,
+// it's the mean of the curve, which is the mean of its control points since
+// every Bernstein polynomial integrates to the same value.
+fn reduce_points<P: Coordinates>(points: &[P], degree: usize) -> Vec<P> {
+    let mut reduced = points.to_vec();
+    while reduced.len() <= degree {
+        reduced = elevate_points(&reduced);
+    }
+
+    let n = points.len() - 1;
+    if n <= degree {
+        return reduced;
+    }
+
+    if degree == 0 {
+        let sum = points.iter().fold(P::ZERO, |sum, &p| add(sum, p));
+        return vec![scale(sum, 1. / points.len() as f64)];
+    }
+
+    let m = degree;
+    let gram = product_ratios(m, m);
+    let mixed = product_ratios(m, n);
+    let (first, last) = (points[0], points[n]);
+
+    let inner = m - 1;
+    let mut matrix = vec![vec![0.; inner]; inner];
+    let mut rhs = vec![P::ZERO; inner];
+    for i in 1..m {
+        for j in 1..m {
+            matrix[i - 1][j - 1] = gram(i, j);
+        }
+
+        let row = &mut rhs[i - 1];
+        for (k, p) in points.iter().enumerate() {
+            for (x, y) in row.as_mut().iter_mut().zip(p.as_ref()) {
+                *x += mixed(i, k) * y * (2 * m + 1) as f64 / (m + n + 1) as f64;
+            }
+        }
+        let ends = first.as_ref().iter().zip(last.as_ref());
+        for (x, (a, b)) in row.as_mut().iter_mut().zip(ends) {
+            *x -= gram(i, 0) * a + gram(i, m) * b;
+        }
+    }
+
+    let mut solution = vec![first];
+    solution.extend(solve(matrix, rhs));
+    solution.push(last);
+    solution
+}
+
+// Gaussian elimination with partial pivoting, one right hand side per axis
+fn solve<P: Coordinates>(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<P>) -> Vec<P> {
+    let n = rhs.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&a, &b| {
+                matrix[a][col]
+                    .abs()
+                    .partial_cmp(&matrix[b][col].abs())
+                    .unwrap()
+            })
+            .unwrap();
+        matrix.swap(col, pivot);
+        rhs.swap(col, pivot);
+
+        let pivot_row = matrix[col].clone();
+        for row in col + 1..n {
+            let factor = matrix[row][col] / pivot_row[col];
+            for (x, y) in matrix[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *x -= factor * y;
+            }
+            let pivot_rhs = rhs[col];
+            for (x, y) in rhs[row].as_mut().iter_mut().zip(pivot_rhs.as_ref()) {
+                *x -= factor * y;
+            }
+        }
+    }
+
+    for col in (0..n).rev() {
+        for row in 0..col {
+            let factor = matrix[row][col] / matrix[col][col];
+            let pivot_rhs = rhs[col];
+            for (x, y) in rhs[row].as_mut().iter_mut().zip(pivot_rhs.as_ref()) {
+                *x -= factor * y;
+            }
+        }
+        for x in rhs[col].as_mut() {
+            *x /= matrix[col][col];
+        }
+    }
+    rhs
+}
+
+// Largest distance between the curve and its reduction. Their difference is
+// a curve too, so it's bounded by its largest control point once both have
+// the same degree.
+fn reduction_error<P: Coordinates>(points: &[P], reduced: &[P]) -> f64 {
+    let (mut a, mut b) = (points.to_vec(), reduced.to_vec());
+    while a.len() < b.len() {
+        a = elevate_points(&a);
+    }
+    while b.len() < a.len() {
+        b = elevate_points(&b);
+    }
+
+    a.iter()
+        .zip(&b)
+        .map(|(&a, &b)| norm(sub(a, b)))
+        .fold(0., f64::max)
+}
+
+fn reduce_into<P: Coordinates>(
+    points: &[P],
+    degree: usize,
+    tolerance: f64,
+    depth: usize,
+    out: &mut Vec<Vec<P>>,
+) {
+    let reduced = reduce_points(points, degree);
+    if depth == 0 || reduction_error(points, &reduced) <= tolerance {
+        out.push(reduced);
+        return;
+    }
+
+    let (left, right) = split_points(points, 0.5);
+    reduce_into(&left, degree, tolerance, depth - 1, out);
+    reduce_into(&right, degree, tolerance, depth - 1, out);
+}
+
+// Parameters in (0, 1) where the polynomial with the given Bernstein
+// coefficients changes sign, in increasing order. By the variation diminishing
+// property there's no root while all the coefficients share a sign, so the
+// interval is halved until that's the case or it's too small to matter.
+fn bernstein_roots(coeffs: &[f64]) -> Vec<f64> {
+    let mut roots = Vec::new();
+    if !coeffs.is_empty() {
+        find_roots(coeffs, 0., 1., &mut roots);
+    }
+    roots
+}
+
+fn find_roots(coeffs: &[f64], a: f64, b: f64, roots: &mut Vec<f64>) {
+    let same_sign = coeffs.iter().all(|&c| c > 0.) || coeffs.iter().all(|&c| c < 0.);
+    if same_sign || coeffs.iter().all(|&c| c == 0.) {
+        return;
+    }
+
+    // Values at both ends of the interval
+    let (first, last) = (coeffs[0], coeffs[coeffs.len() - 1]);
+
+    if b - a < 1e-10 {
+        if first * last < 0. {
+            roots.push((a + b) / 2.);
+        } else if last == 0. && first != 0. && b < 1. {
+            roots.push(b);
+        }
+        return;
+    }
+
+    let (left, right) = split_scalars(coeffs, 0.5);
+    let middle = (a + b) / 2.;
+    find_roots(&left, a, middle, roots);
+    find_roots(&right, middle, b, roots);
+}
+
+fn split_scalars(coeffs: &[f64], t: f64) -> (Vec<f64>, Vec<f64>) {
+    let n = coeffs.len();
+    let mut level = coeffs.to_vec();
+    let mut left = Vec::with_capacity(n);
+    let mut right = Vec::with_capacity(n);
+
+    for _ in 0..n {
+        left.push(level[0]);
+        right.push(level[level.len() - 1]);
+        level = level
+            .windows(2)
+            .map(|w| (1. - t) * w[0] + t * w[1])
+            .collect();
+    }
+
+    right.reverse();
+    (left, right)
+}
+
+// Nodes in [0, 1] and weights of the 8 point Gauss-Legendre rule, exact for
+// polynomials up to degree 15
+const GAUSS_LEGENDRE: [(f64, f64); 4] = [
+    (0.1834346424956498, 0.362683783378362),
+    (0.525532409916329, 0.31370664587788727),
+    (0.7966664774136267, 0.22238103445337448),
+    (0.9602898564975363, 0.10122853629037626),
+];
+
+fn speed<P: Coordinates>(scratch: &mut Vec<P>, hodograph: &[P], t: f64) -> f64 {
+    norm(de_casteljau(scratch, hodograph, t))
+}
+
+fn gauss_legendre<P: Coordinates>(scratch: &mut Vec<P>, hodograph: &[P], a: f64, b: f64) -> f64 {
+    let (middle, half) = ((a + b) / 2., (b - a) / 2.);
+    GAUSS_LEGENDRE
+        .iter()
+        .map(|&(x, w)| {
+            let left = speed(scratch, hodograph, middle - half * x);
+            let right = speed(scratch, hodograph, middle + half * x);
+            w * (left + right)
+        })
+        .sum::<f64>()
+        * half
+}
+
+// Length of the curve between `a` and `b`. The speed isn't a polynomial, and
+// has kinks at cusps, so the interval is halved until both halves agree with
+// the whole.
+fn arc_length<P: Coordinates>(scratch: &mut Vec<P>, hodograph: &[P], a: f64, b: f64) -> f64 {
+    fn refine<P: Coordinates>(
+        scratch: &mut Vec<P>,
+        hodograph: &[P],
+        a: f64,
+        b: f64,
+        whole: f64,
+        depth: usize,
+    ) -> f64 {
+        let middle = (a + b) / 2.;
+        let left = gauss_legendre(scratch, hodograph, a, middle);
+        let right = gauss_legendre(scratch, hodograph, middle, b);
+
+        if depth == 0 || (left + right - whole).abs() <= 1e-9 * (left + right).max(1.) {
+            return left + right;
+        }
+
+        refine(scratch, hodograph, a, middle, left, depth - 1)
+            + refine(scratch, hodograph, middle, b, right, depth - 1)
+    }
+
+    if hodograph.is_empty() || a == b {
+        return 0.;
+    }
+
+    let whole = gauss_legendre(scratch, hodograph, a, b);
+    refine(scratch, hodograph, a, b, whole, 16)
+}
+
+fn coordinate<P: Coordinates>(points: &[P], axis: usize) -> Vec<f64> {
+    points.iter().map(|p| p.as_ref()[axis]).collect()
+}
+
+fn control_box<P: Coordinates>(points: &[P]) -> (P, P) {
+    let (mut min, mut max) = (P::ZERO, P::ZERO);
+    min.as_mut().fill(f64::INFINITY);
+    max.as_mut().fill(f64::NEG_INFINITY);
+
+    for p in points {
+        let bounds = min.as_mut().iter_mut().zip(max.as_mut());
+        for ((lo, hi), &x) in bounds.zip(p.as_ref()) {
+            *lo = lo.min(x);
+            *hi = hi.max(x);
+        }
+    }
+    (min, max)
+}
+
+// Parameters along both segments where they cross, if they aren't parallel
+fn segment_intersection(a: (Point, Point), b: (Point, Point)) -> Option<(f64, f64)> {
+    let da = [a.1[0] - a.0[0], a.1[1] - a.0[1]];
+    let db = [b.1[0] - b.0[0], b.1[1] - b.0[1]];
+    let denominator = cross(da, db);
+    if denominator == 0. {
+        return None;
+    }
+
+    let offset = [b.0[0] - a.0[0], b.0[1] - a.0[1]];
+    let s = cross(offset, db) / denominator;
+    let u = cross(offset, da) / denominator;
+    if (0. ..=1.).contains(&s) && (0. ..=1.).contains(&u) {
+        Some((s, u))
+    } else {
+        None
+    }
+}
+
+// Pieces of both curves, with their parameter ranges, are split in half while
+// their control boxes overlap. Once both are flat they're intersected as
+// segments, which also stops the recursion where the curves overlap.
+fn intersect_into(
+    a: (&[Point], f64, f64),
+    b: (&[Point], f64, f64),
+    tolerance: f64,
+    depth: usize,
+    out: &mut Vec<(f64, f64)>,
+) {
+    let ((a_min, a_max), (b_min, b_max)) = (control_box(a.0), control_box(b.0));
+    let separated = (0..2).any(|i| a_max[i] < b_min[i] || b_max[i] < a_min[i]);
+    if separated {
+        return;
+    }
+
+    let flat = |points: &[Point]| flatness(points) <= tolerance;
+    if depth == 0 || (flat(a.0) && flat(b.0)) {
+        let chord = |points: &[Point]| (points[0], points[points.len() - 1]);
+        if let Some((s, u)) = segment_intersection(chord(a.0), chord(b.0)) {
+            out.push((a.1 + (a.2 - a.1) * s, b.1 + (b.2 - b.1) * u));
+        }
+        return;
+    }
+
+    // Split the larger piece only, so they shrink at the same pace
+    let size = |(min, max): (Point, Point)| (max[0] - min[0]).max(max[1] - min[1]);
+    if size((a_min, a_max)) >= size((b_min, b_max)) {
+        let (left, right) = split_points(a.0, 0.5);
+        let middle = (a.1 + a.2) / 2.;
+        intersect_into((&left, a.1, middle), b, tolerance, depth - 1, out);
+        intersect_into((&right, middle, a.2), b, tolerance, depth - 1, out);
+    } else {
+        let (left, right) = split_points(b.0, 0.5);
+        let middle = (b.1 + b.2) / 2.;
+        intersect_into(a, (&left, b.1, middle), tolerance, depth - 1, out);
+        intersect_into(a, (&right, middle, b.2), tolerance, depth - 1, out);
+    }
+}
+
+// Curve at a fixed distance along the normal of another one, B(t) + d N(t).
+// Its derivative is B'(t) (1 - d κ(t)), so it runs along the curve while
+// d κ < 1 and against it past there, leaving a cusp in between.
+struct Offset {
+    points: Vec<Point>,
+    first: Vec<Point>,
+    second: Vec<Point>,
+    distance: f64,
+}
+
+impl Offset {
+    fn new(points: Vec<Point>, distance: f64) -> Self {
+        let first = hodograph(&points);
+        let second = hodograph(&first);
+        Self {
+            points,
+            first,
+            second,
+            distance,
+        }
+    }
+
+    fn derivatives(&self, scratch: &mut Vec<Point>, t: f64) -> (Point, Point) {
+        let d1 = de_casteljau(scratch, &self.first, t);
+        let d2 = if self.second.is_empty() {
+            [0.; 2]
+        } else {
+            de_casteljau(scratch, &self.second, t)
+        };
+        (d1, d2)
+    }
+
+    // |B'|³ (1 - d κ), with the same sign as the derivative of the offset
+    // along the curve's but without the division
+    fn stretch(&self, scratch: &mut Vec<Point>, t: f64) -> f64 {
+        let (d1, d2) = self.derivatives(scratch, t);
+        norm(d1).powi(3) - self.distance * cross(d1, d2)
+    }
+
+    // Point of the offset at `t` and its derivative there
+    fn at(&self, scratch: &mut Vec<Point>, t: f64) -> (Point, Point) {
+        let p = de_casteljau(scratch, &self.points, t);
+        let [x, y] = unit_tangent(scratch, &self.first, t);
+        let point = [p[0] - self.distance * y, p[1] + self.distance * x];
+
+        let (d1, d2) = self.derivatives(scratch, t);
+        let speed = norm(d1);
+        if speed == 0. {
+            return (point, d1);
+        }
+
+        let curvature = cross(d1, d2) / (speed * speed * speed);
+        (point, scale(d1, 1. - self.distance * curvature))
+    }
+
+    // Parameters in (0, 1) of the cusps, where the radius of curvature on the
+    // side of the offset matches the distance. They're bracketed by sampling
+    // and then bisected.
+    fn cusps(&self) -> Vec<f64> {
+        let mut scratch = Vec::with_capacity(self.points.len());
+        let samples = 64 * self.points.len();
+        let mut cusps = Vec::new();
+
+        let mut previous = (0., self.stretch(&mut scratch, 0.));
+        for i in 1..=samples {
+            let t = i as f64 / samples as f64;
+            let current = (t, self.stretch(&mut scratch, t));
+            if previous.1 * current.1 < 0. {
+                let (mut lo, mut hi) = (previous.0, t);
+                for _ in 0..50 {
+                    let middle = (lo + hi) / 2.;
+                    if self.stretch(&mut scratch, middle) * previous.1 > 0. {
+                        lo = middle;
+                    } else {
+                        hi = middle;
+                    }
+                }
+                cusps.push((lo + hi) / 2.);
+            }
+            previous = current;
+        }
+        cusps
+    }
+
+    // Cubic with the ends and derivatives of the offset between `t0` and
+    // `t1`, split in half until it's within `tolerance` of the offset at the
+    // same parameters
+    fn fit_into(&self, t0: f64, t1: f64, tolerance: f64, depth: usize, out: &mut Vec<Vec<Point>>) {
+        let mut scratch = Vec::with_capacity(self.points.len());
+        let ((p0, v0), (p1, v1)) = (self.at(&mut scratch, t0), self.at(&mut scratch, t1));
+        let h = (t1 - t0) / 3.;
+        let cubic = vec![
+            p0,
+            [p0[0] + h * v0[0], p0[1] + h * v0[1]],
+            [p1[0] - h * v1[0], p1[1] - h * v1[1]],
+            p1,
+        ];
+
+        let error = (1..8)
+            .map(|i| {
+                let s = i as f64 / 8.;
+                let (expected, _) = self.at(&mut scratch, t0 + (t1 - t0) * s);
+                norm(sub(de_casteljau(&mut scratch, &cubic, s), expected))
+            })
+            .fold(0., f64::max);
+
+        if depth == 0 || error <= tolerance {
+            out.push(cubic);
+            return;
+        }
+
+        let middle = (t0 + t1) / 2.;
+        self.fit_into(t0, middle, tolerance, depth - 1, out);
+        self.fit_into(middle, t1, tolerance, depth - 1, out);
+    }
+}
+
+// Polynomial curve through control points of any dimension, plane curves by
+// default. Queries that only make sense in the plane, like normals or
+// intersections, are only there for `Bezier<Vertex>`.
 #[derive(Debug, Clone)]
-pub struct PascalTable {
-    tab: Vec<Vec<usize>>,
+pub struct Bezier<V = Vertex> {
+    steps: usize,
+    points: Vec<V>,
 }
 
-impl PascalTable {
-    #[allow(clippy::new_without_default)]
+impl<V> Default for Bezier<V> {
+    fn default() -> Self {
+        Self {
+            steps: 0,
+            points: Vec::new(),
+        }
+    }
+}
+
+impl<V: ControlPoint> Bezier<V> {
     pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_steps(self, steps: usize) -> Self {
+        Self { steps, ..self }
+    }
+
+    pub fn with_points(self, points: &[V]) -> Self {
         Self {
-            tab: vec![
-                vec![1],
-                vec![1, 1],
-                vec![1, 2, 1],
-                vec![1, 3, 3, 1],
-                vec![1, 4, 6, 4, 1],
-                vec![1, 5, 10, 10, 5, 1],
-                vec![1, 6, 15, 20, 15, 6, 1],
-            ],
-        }
-    }
-
-    pub fn get(&mut self, n: usize, k: usize) -> usize {
-        while n >= self.tab.len() {
-            let mut new_row = vec![1; self.tab.len() + 1];
-            let prev = self.tab.len() - 1;
-            for i in 1..self.tab.len() {
-                new_row[i] = self.tab[prev][i - 1] + self.tab[prev][i];
+            points: Vec::from(points),
+            ..self
+        }
+    }
+
+    pub fn push_point(&mut self, vertex: V) {
+        self.points.push(vertex)
+    }
+
+    pub fn set_point(&mut self, index: usize, vertex: V) {
+        self.points[index] = vertex;
+    }
+
+    // Inserts before the point at `index`, or at the end if it's the number
+    // of points
+    pub fn insert_point(&mut self, index: usize, vertex: V) {
+        self.points.insert(index, vertex);
+    }
+
+    pub fn remove_point(&mut self, index: usize) -> V {
+        self.points.remove(index)
+    }
+
+    pub fn clear_points(&mut self) {
+        self.points.clear();
+    }
+
+    pub fn points(&self) -> &[V] {
+        &self.points
+    }
+
+    // Index of the control point closest to `vertex`, if it's within `radius`
+    pub fn hit_point(&self, vertex: V, radius: f32) -> Option<usize> {
+        let q = vertex.coordinates();
+        self.points
+            .iter()
+            .map(|p| norm(sub(p.coordinates(), q)))
+            .enumerate()
+            .filter(|&(_, distance)| distance <= radius as f64)
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .map(|(i, _)| i)
+    }
+
+    fn control_points(&self) -> Vec<V::Coordinates> {
+        self.points.iter().map(V::coordinates).collect()
+    }
+
+    fn with_control_points(&self, points: &[V::Coordinates]) -> Self {
+        Self {
+            steps: self.steps,
+            points: points.iter().copied().map(V::from_coordinates).collect(),
+        }
+    }
+
+    // Point of the curve at `t`, None without points
+    pub fn single(&self, t: f32) -> Option<V> {
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let mut scratch = Vec::with_capacity(self.points.len());
+        let point = de_casteljau(&mut scratch, &self.control_points(), t as f64);
+        Some(V::from_coordinates(point))
+    }
+
+    // Every polygon of de Casteljau's algorithm at `t`, starting with the
+    // control points. Each level interpolates the one before at `t` and has
+    // one point less, the last one is the point of the curve at `t`.
+    pub fn de_casteljau_levels(&self, t: f32) -> Vec<Vec<V>> {
+        let mut level = self.control_points();
+        let mut levels = Vec::with_capacity(level.len());
+        while !level.is_empty() {
+            levels.push(level.iter().map(|&p| V::from_coordinates(p)).collect());
+            level = level
+                .windows(2)
+                .map(|w| lerp(w[0], w[1], t as f64))
+                .collect();
+        }
+        levels
+    }
+
+    // Evenly spaced samples at t = i / steps for i in 0..=steps, so both
+    // endpoints are always included. Zero steps yield nothing.
+    pub fn samples(&self, steps: usize) -> Samples<V> {
+        Samples {
+            points: self.control_points(),
+            scratch: Vec::with_capacity(self.points.len()),
+            steps,
+            next: 0,
+        }
+    }
+
+    // Fills the whole buffer with evenly spaced samples, from the start of the
+    // curve in the first slot to its end in the last one
+    pub fn sample_into(&self, buffer: &mut [V]) {
+        if self.points.is_empty() {
+            return;
+        }
+
+        let steps = buffer.len().saturating_sub(1).max(1);
+        for (slot, vertex) in buffer.iter_mut().zip(self.forward_samples(steps)) {
+            *slot = vertex;
+        }
+    }
+
+    // The same samples as `samples` up to rounding, with a single addition per
+    // control point and step for curves up to cubics. It starts from the value
+    // and the forward differences of the curve at t = 0, each step adds every
+    // difference to the one before it. Rounding errors add up along the way
+    // so it's kept to low degrees, the last sample is the end point itself.
+    // Curves of higher degree are sampled with `samples` instead.
+    pub fn forward_samples(&self, steps: usize) -> ForwardSamples<V> {
+        let n = self.points.len();
+        if n > 4 {
+            return ForwardSamples {
+                differences: Vec::new(),
+                end: V::Coordinates::ZERO,
+                steps,
+                next: 0,
+                exact: Some(self.samples(steps)),
+            };
+        }
+
+        let points = self.control_points();
+        let mut scratch = Vec::with_capacity(n);
+        let h = 1. / steps.max(1) as f64;
+
+        // Values at 0, h, ..., (n - 1) h, then differences of differences
+        let mut differences: Vec<V::Coordinates> = (0..n)
+            .map(|i| de_casteljau(&mut scratch, &points, i as f64 * h))
+            .collect();
+        for level in 1..n {
+            for i in (level..n).rev() {
+                differences[i] = sub(differences[i], differences[i - 1]);
             }
+        }
+
+        ForwardSamples {
+            differences,
+            end: points.last().copied().unwrap_or(V::Coordinates::ZERO),
+            steps,
+            next: 0,
+            exact: None,
+        }
+    }
+
+    // Both halves of the curve at `t`, each one a curve of the same degree
+    pub fn split(&self, t: f32) -> (Self, Self) {
+        let (left, right) = split_points(&self.control_points(), t as f64);
+        (
+            self.with_control_points(&left),
+            self.with_control_points(&right),
+        )
+    }
+
+    // The part of the curve between `t0` and `t1`, reversed if `t0` > `t1`
+    pub fn segment(&self, t0: f32, t1: f32) -> Self {
+        let points = segment_points(&self.control_points(), t0 as f64, t1 as f64);
+        self.with_control_points(&points)
+    }
+
+    // Polyline through the curve that strays at most `tolerance` from it,
+    // subdividing only where the curve isn't flat enough yet
+    pub fn flatten(&self, tolerance: f32) -> Vec<V> {
+        let points = self.control_points();
+        let mut out = Vec::new();
+        if let Some(&first) = points.first() {
+            out.push(first);
+        }
+
+        if points.len() >= 2 {
+            let tolerance = tolerance.max(f32::EPSILON) as f64;
+            flatten_into(&points, |p| p, tolerance, 16, &mut out);
+        }
+        out.into_iter().map(V::from_coordinates).collect()
+    }
 
-            self.tab.push(new_row);
+    // The derivative of the curve, its hodograph, as a curve one degree lower
+    pub fn derivative(&self) -> Self {
+        self.with_control_points(&hodograph(&self.control_points()))
+    }
+
+    // Unit tangent at `t`. Where the first derivative vanishes, like at an end
+    // with a repeated control point, the direction is taken from the next
+    // derivative that doesn't. Zero if the curve is a single point.
+    pub fn tangent(&self, t: f32) -> V {
+        let mut scratch = Vec::with_capacity(self.points.len());
+        let derivative = hodograph(&self.control_points());
+        V::from_coordinates(unit_tangent(&mut scratch, &derivative, t as f64))
+    }
+
+    pub fn length(&self) -> f32 {
+        let mut scratch = Vec::with_capacity(self.points.len());
+        arc_length(&mut scratch, &hodograph(&self.control_points()), 0., 1.) as f32
+    }
+
+    // Cumulative lengths to map between `t` and the distance along the curve
+    pub fn arc_length_table(&self) -> ArcLengthTable<V> {
+        let hodograph = hodograph(&self.control_points());
+        let segments = 16 * hodograph.len().max(1);
+        let mut scratch = Vec::with_capacity(hodograph.len());
+
+        let mut lengths = Vec::with_capacity(segments + 1);
+        lengths.push(0.);
+        for i in 0..segments {
+            let a = i as f64 / segments as f64;
+            let b = (i + 1) as f64 / segments as f64;
+            let length = lengths[i] + arc_length(&mut scratch, &hodograph, a, b);
+            lengths.push(length);
         }
 
-        self.tab[n][k]
+        ArcLengthTable { hodograph, lengths }
+    }
+
+    // Samples at `steps` equal distances along the curve, both endpoints
+    // included like `samples`. Zero steps yield nothing.
+    pub fn uniform_samples(&self, steps: usize) -> Vec<V> {
+        if steps == 0 || self.points.is_empty() {
+            return Vec::new();
+        }
+
+        let table = self.arc_length_table();
+        let total = table.total_length();
+        let points = self.control_points();
+        let mut scratch = Vec::with_capacity(points.len());
+
+        (0..=steps)
+            .map(|i| {
+                let t = table.parameter(total * i as f64 / steps as f64);
+                V::from_coordinates(de_casteljau(&mut scratch, &points, t))
+            })
+            .collect()
+    }
+
+    // The same curve with one more control point
+    pub fn elevate(&self) -> Self {
+        self.with_control_points(&elevate_points(&self.control_points()))
+    }
+
+    // The same curve with `degree` + 1 control points, it's left as is if it
+    // already has that many or more
+    pub fn elevate_to(&self, degree: usize) -> Self {
+        let mut points = self.control_points();
+        while !points.is_empty() && points.len() <= degree {
+            points = elevate_points(&points);
+        }
+        self.with_control_points(&points)
+    }
+
+    // Least squares approximation with `degree` + 1 control points, keeping
+    // the endpoints. It's exact when the curve was elevated from that degree.
+    // Degree zero gives the mean point of the curve.
+    pub fn reduce_to(&self, degree: usize) -> Self {
+        let points = self.control_points();
+        if points.is_empty() {
+            return self.clone();
+        }
+        self.with_control_points(&reduce_points(&points, degree))
+    }
+
+    // Chain of curves of the given degree, joined end to end, that stays
+    // within `tolerance` of this one. Pieces are reduced and split in half
+    // until they're close enough.
+    pub fn to_degree(&self, degree: usize, tolerance: f32) -> Vec<Self> {
+        let points = self.control_points();
+        if points.is_empty() {
+            return Vec::new();
+        }
+
+        let mut pieces = Vec::new();
+        let tolerance = tolerance.max(f32::EPSILON) as f64;
+        reduce_into(&points, degree.max(1), tolerance, 16, &mut pieces);
+
+        pieces
+            .iter()
+            .map(|piece| self.with_control_points(piece))
+            .collect()
+    }
+
+    pub fn to_cubics(&self, tolerance: f32) -> Vec<Self> {
+        self.to_degree(3, tolerance)
+    }
+
+    pub fn to_quadratics(&self, tolerance: f32) -> Vec<Self> {
+        self.to_degree(2, tolerance)
+    }
+
+    // Smallest axis aligned box holding the curve, as its lowest and highest
+    // corners. Besides the ends, only the extremes of each coordinate, where
+    // its derivative changes sign, can touch the box. None without points.
+    pub fn bounding_box(&self) -> Option<(V, V)> {
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let points = self.control_points();
+        let derivative = hodograph(&points);
+        let mut scratch = Vec::with_capacity(points.len());
+
+        let mut ts = vec![0., 1.];
+        for axis in 0..V::Coordinates::ZERO.as_ref().len() {
+            ts.extend(bernstein_roots(&coordinate(&derivative, axis)));
+        }
+
+        let extremes: Vec<V::Coordinates> = ts
+            .iter()
+            .map(|&t| de_casteljau(&mut scratch, &points, t))
+            .collect();
+        let (min, max) = control_box(&extremes);
+        Some((V::from_coordinates(min), V::from_coordinates(max)))
+    }
+
+    // Parameter and position of the point of the curve closest to `point`.
+    // It's either an end or a root of (B(t) - point) . B'(t). None without
+    // points.
+    pub fn nearest(&self, point: V) -> Option<(f32, V)> {
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let q = point.coordinates();
+        let points = self.control_points();
+        let derivative = hodograph(&points);
+
+        let mut ts = vec![0., 1.];
+        if !derivative.is_empty() {
+            let offset: Vec<V::Coordinates> = points.iter().map(|&p| sub(p, q)).collect();
+            let mut dot = Vec::new();
+            for axis in 0..q.as_ref().len() {
+                let product =
+                    bernstein_product(&coordinate(&offset, axis), &coordinate(&derivative, axis));
+                dot.resize(product.len(), 0.);
+                for (sum, x) in dot.iter_mut().zip(product) {
+                    *sum += x;
+                }
+            }
+            ts.extend(bernstein_roots(&dot));
+        }
+
+        let mut scratch = Vec::with_capacity(points.len());
+        let (t, p) = ts
+            .into_iter()
+            .map(|t| (t, de_casteljau(&mut scratch, &points, t)))
+            .min_by(|&(_, a), &(_, b)| norm(sub(a, q)).partial_cmp(&norm(sub(b, q))).unwrap())
+            .unwrap();
+
+        Some((t as f32, V::from_coordinates(p)))
+    }
+
+    pub fn interpolate(&self) -> Option<Vec<V>> {
+        if self.steps == 0 || self.points.len() < 2 {
+            return None;
+        }
+
+        Some(self.forward_samples(self.steps).collect())
     }
 }
 
-lazy_static! {
-    static ref PASCAL_TABLE: Mutex<PascalTable> = Mutex::new(PascalTable::new());
+impl Bezier {
+    // The unit tangent rotated a quarter turn counter-clockwise
+    pub fn normal(&self, t: f32) -> Vertex {
+        let [x, y] = to_point(&self.tangent(t));
+        to_vertex([-y, x])
+    }
+
+    // Signed curvature at `t`, positive where the curve turns
+    // counter-clockwise. Zero where the first derivative vanishes.
+    pub fn curvature(&self, t: f32) -> f32 {
+        let mut scratch = Vec::with_capacity(self.points.len());
+        let first = hodograph(&self.control_points());
+        let second = hodograph(&first);
+        if second.is_empty() {
+            return 0.;
+        }
+
+        let d1 = de_casteljau(&mut scratch, &first, t as f64);
+        let d2 = de_casteljau(&mut scratch, &second, t as f64);
+        let speed = d1[0].hypot(d1[1]);
+        if speed == 0. {
+            return 0.;
+        }
+
+        (cross(d1, d2) / (speed * speed * speed)) as f32
+    }
+
+    // Parameters in (0, 1) where the curvature changes sign, found as the
+    // roots of B'(t) x B''(t)
+    pub fn inflections(&self) -> Vec<f32> {
+        let first = hodograph(&self.control_points());
+        let second = hodograph(&first);
+        if second.is_empty() {
+            return Vec::new();
+        }
+
+        let a = bernstein_product(&coordinate(&first, 0), &coordinate(&second, 1));
+        let b = bernstein_product(&coordinate(&first, 1), &coordinate(&second, 0));
+        let coeffs: Vec<f64> = a.iter().zip(&b).map(|(a, b)| a - b).collect();
+
+        bernstein_roots(&coeffs)
+            .into_iter()
+            .map(|t| t as f32)
+            .collect()
+    }
+
+    // Curves parallel to this one, `distance` away along its normal so on
+    // its left for positive distances, as cubics within `tolerance` of the
+    // true offset. Where the curve bends tighter than the distance on that
+    // side the offset has cusps and runs backwards between them, so it's split
+    // at the cusps to keep every piece smooth. A line stays a line.
+    pub fn offset(&self, distance: f32, tolerance: f32) -> Vec<Bezier> {
+        let points = self.control_points();
+        if points.len() < 2 {
+            return Vec::new();
+        }
+
+        let offset = Offset::new(points, distance as f64);
+        let mut pieces = Vec::new();
+        if offset.points.len() == 2 {
+            let mut scratch = Vec::with_capacity(2);
+            let (start, _) = offset.at(&mut scratch, 0.);
+            let (end, _) = offset.at(&mut scratch, 1.);
+            pieces.push(vec![start, end]);
+        } else {
+            let mut breaks = vec![0.];
+            breaks.extend(offset.cusps());
+            breaks.push(1.);
+
+            let tolerance = tolerance.max(f32::EPSILON) as f64;
+            for w in breaks.windows(2) {
+                offset.fit_into(w[0], w[1], tolerance, 16, &mut pieces);
+            }
+        }
+
+        pieces
+            .iter()
+            .map(|piece| self.with_control_points(piece))
+            .collect()
+    }
+
+    // Parameters where the curve crosses the segment from `from` to `to`, in
+    // increasing order. They are the roots of the signed distance to the
+    // segment's line, whose control points are those of the curve's.
+    pub fn intersect_line(&self, from: Vertex, to: Vertex) -> Vec<f32> {
+        let points = self.control_points();
+        if points.is_empty() {
+            return Vec::new();
+        }
+
+        let (a, b) = (to_point(&from), to_point(&to));
+        let direction = [b[0] - a[0], b[1] - a[1]];
+        let length = direction[0] * direction[0] + direction[1] * direction[1];
+
+        let distances: Vec<f64> = points
+            .iter()
+            .map(|p| cross(direction, [p[0] - a[0], p[1] - a[1]]))
+            .collect();
+
+        let mut ts = bernstein_roots(&distances);
+        if distances[0] == 0. {
+            ts.insert(0, 0.);
+        }
+        if distances.len() > 1 && distances[distances.len() - 1] == 0. {
+            ts.push(1.);
+        }
+
+        let mut scratch = Vec::with_capacity(points.len());
+        ts.into_iter()
+            .filter(|&t| {
+                let p = de_casteljau(&mut scratch, &points, t);
+                let along = (p[0] - a[0]) * direction[0] + (p[1] - a[1]) * direction[1];
+                length > 0. && (0. ..=length).contains(&along)
+            })
+            .map(|t| t as f32)
+            .collect()
+    }
+
+    // Pairs of parameters, on this curve and on `other`, where both curves
+    // meet, ordered along this curve. Where they overlap there's a run of
+    // closely spaced pairs along the shared stretch.
+    pub fn intersect(&self, other: &Bezier) -> Vec<(f32, f32)> {
+        let (a, b) = (self.control_points(), other.control_points());
+        if a.is_empty() || b.is_empty() {
+            return Vec::new();
+        }
+
+        let (min, max) = control_box(&[a.clone(), b.clone()].concat());
+        let extent = (max[0] - min[0]).max(max[1] - min[1]);
+        let tolerance = (extent * 1e-7).max(1e-12);
+
+        let mut found = Vec::new();
+        intersect_into((&a, 0., 1.), (&b, 0., 1.), tolerance, 64, &mut found);
+        found.sort_by(|x, y| x.partial_cmp(y).unwrap());
+
+        // Crossings on the boundary between pieces are found from both sides
+        let mut out: Vec<(f64, f64)> = Vec::new();
+        for (s, u) in found {
+            match out.last() {
+                Some(&(ps, pu)) if (s - ps).abs() < 1e-6 && (u - pu).abs() < 1e-6 => {}
+                _ => out.push((s, u)),
+            }
+        }
+        out.iter().map(|&(s, u)| (s as f32, u as f32)).collect()
+    }
+}
+
+// Lengths from the start of the curve at evenly spaced values of `t`. Lookups
+// start from the table and are refined against the curve itself, so the table
+// only needs to be fine enough to bracket the answer.
+#[derive(Debug, Clone)]
+pub struct ArcLengthTable<V: ControlPoint = Vertex> {
+    hodograph: Vec<V::Coordinates>,
+    lengths: Vec<f64>,
+}
+
+impl<V: ControlPoint> ArcLengthTable<V> {
+    fn total_length(&self) -> f64 {
+        self.lengths[self.lengths.len() - 1]
+    }
+
+    fn segment_of(&self, t: f64) -> (usize, f64) {
+        let segments = self.lengths.len() - 1;
+        let i = ((t * segments as f64) as usize).min(segments - 1);
+        (i, i as f64 / segments as f64)
+    }
+
+    fn length_between(&self, i: usize, t0: f64, t: f64) -> f64 {
+        let mut scratch = Vec::with_capacity(self.hodograph.len());
+        self.lengths[i] + arc_length(&mut scratch, &self.hodograph, t0, t)
+    }
+
+    // Newton's method on s(t) - length, kept inside the bracketing segment by
+    // falling back to bisection
+    fn parameter(&self, length: f64) -> f64 {
+        let total = self.total_length();
+        if length <= 0. || total == 0. {
+            return 0.;
+        }
+        if length >= total {
+            return 1.;
+        }
+
+        let segments = self.lengths.len() - 1;
+        let i = match self
+            .lengths
+            .binary_search_by(|probe| probe.partial_cmp(&length).unwrap())
+        {
+            Ok(i) => return i as f64 / segments as f64,
+            Err(i) => i - 1,
+        };
+
+        let t0 = i as f64 / segments as f64;
+        let (mut lo, mut hi) = (t0, (i + 1) as f64 / segments as f64);
+        let (l0, l1) = (self.lengths[i], self.lengths[i + 1]);
+        let mut t = lo + (hi - lo) * (length - l0) / (l1 - l0);
+
+        let mut scratch = Vec::with_capacity(self.hodograph.len());
+        for _ in 0..32 {
+            let error = self.length_between(i, t0, t) - length;
+            if error.abs() <= 1e-9 * total.max(1.) {
+                break;
+            }
+
+            if error > 0. {
+                hi = t;
+            } else {
+                lo = t;
+            }
+
+            let speed = speed(&mut scratch, &self.hodograph, t);
+            let next = t - error / speed;
+            t = if speed > 0. && next > lo && next < hi {
+                next
+            } else {
+                (lo + hi) / 2.
+            };
+        }
+        t
+    }
+
+    pub fn length(&self) -> f32 {
+        self.total_length() as f32
+    }
+
+    // Distance along the curve from its start to `t`
+    pub fn length_at(&self, t: f32) -> f32 {
+        let t = (t as f64).clamp(0., 1.);
+        let (i, t0) = self.segment_of(t);
+        self.length_between(i, t0, t) as f32
+    }
+
+    // Value of `t` at the given distance along the curve, clamped to its ends
+    pub fn t_at(&self, length: f32) -> f32 {
+        self.parameter(length as f64) as f32
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Samples<V: ControlPoint = Vertex> {
+    points: Vec<V::Coordinates>,
+    scratch: Vec<V::Coordinates>,
+    steps: usize,
+    next: usize,
+}
+
+impl<V: ControlPoint> Iterator for Samples<V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<V> {
+        if self.next > self.steps || self.steps == 0 || self.points.is_empty() {
+            return None;
+        }
+
+        let t = self.next as f64 / self.steps as f64;
+        self.next += 1;
+        Some(V::from_coordinates(de_casteljau(
+            &mut self.scratch,
+            &self.points,
+            t,
+        )))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = if self.steps == 0 || self.points.is_empty() {
+            0
+        } else {
+            self.steps + 1 - self.next.min(self.steps + 1)
+        };
+        (left, Some(left))
+    }
+}
+
+impl<V: ControlPoint> ExactSizeIterator for Samples<V> {}
+
+#[derive(Debug, Clone)]
+pub struct ForwardSamples<V: ControlPoint = Vertex> {
+    differences: Vec<V::Coordinates>,
+    end: V::Coordinates,
+    steps: usize,
+    next: usize,
+    // De Casteljau's algorithm at every sample for higher degrees
+    exact: Option<Samples<V>>,
+}
+
+impl<V: ControlPoint> Iterator for ForwardSamples<V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<V> {
+        if let Some(samples) = &mut self.exact {
+            return samples.next();
+        }
+
+        if self.next > self.steps || self.steps == 0 || self.differences.is_empty() {
+            return None;
+        }
+
+        let value = if self.next == self.steps {
+            self.end
+        } else {
+            self.differences[0]
+        };
+        for i in 1..self.differences.len() {
+            self.differences[i - 1] = add(self.differences[i - 1], self.differences[i]);
+        }
+
+        self.next += 1;
+        Some(V::from_coordinates(value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if let Some(samples) = &self.exact {
+            return samples.size_hint();
+        }
+
+        let left = if self.steps == 0 || self.differences.is_empty() {
+            0
+        } else {
+            self.steps + 1 - self.next.min(self.steps + 1)
+        };
+        (left, Some(left))
+    }
 }
 
-fn binomial(n: usize, k: usize) -> usize {
-    PASCAL_TABLE.lock().unwrap().get(n, k)
+impl<V: ControlPoint> ExactSizeIterator for ForwardSamples<V> {}
+
+// Control points of rational curves, their coordinates times their weight
+// with the weight last
+pub type Homogeneous = [f64; 3];
+
+pub fn to_homogeneous(vertex: &Vertex, weight: f32) -> Homogeneous {
+    let [x, y] = to_point(vertex);
+    let w = weight as f64;
+    [x * w, y * w, w]
+}
+
+// The point in the plane, dividing by the weight
+pub fn project(point: Homogeneous) -> [f64; 2] {
+    [point[0] / point[2], point[1] / point[2]]
 }
 
+// Bezier curve with a weight per control point, evaluated as a polynomial
+// curve in homogeneous coordinates and projected back to the plane. With all
+// the weights set to one it's the same curve as `Bezier`, other weights pull
+// the curve towards or away from their control points. Weights should be
+// positive so that the curve stays in the hull of its control points.
 #[derive(Debug, Clone, Default)]
-pub struct Bezier {
+pub struct RationalBezier {
     steps: usize,
     points: Vec<Vertex>,
+    weights: Vec<f32>,
 }
 
-impl Bezier {
+impl RationalBezier {
     pub fn new() -> Self {
         Default::default()
     }
@@ -64,50 +1415,199 @@ impl Bezier {
         Self { steps, ..self }
     }
 
+    // Every point gets a weight of one
     pub fn with_points(self, points: &[Vertex]) -> Self {
         Self {
             points: Vec::from(points),
+            weights: vec![1.; points.len()],
             ..self
         }
     }
 
-    pub fn push_point(&mut self, vertex: Vertex) {
-        println!("New point: {:?}", vertex);
-        self.points.push(vertex)
+    pub fn with_weighted_points(self, points: &[Vertex], weights: &[f32]) -> Self {
+        assert_eq!(points.len(), weights.len(), "One weight per point");
+        Self {
+            points: Vec::from(points),
+            weights: Vec::from(weights),
+            ..self
+        }
+    }
+
+    // Circular arc around `center`, from the `start` angle sweeping `sweep`
+    // radians counter-clockwise (clockwise if negative)
+    pub fn circular_arc(center: Vertex, radius: f32, start: f32, sweep: f32) -> Vec<Self> {
+        Self::elliptical_arc(center, radius, radius, 0., start, sweep)
+    }
+
+    // Arc of the ellipse with radii `rx` and `ry` rotated by `rotation`, with
+    // `start` and `sweep` measured as angles before stretching the circle into
+    // the ellipse. Each piece is an exact conic spanning at most a quarter
+    // turn, since a single quadratic can't reach half a turn.
+    pub fn elliptical_arc(
+        center: Vertex,
+        rx: f32,
+        ry: f32,
+        rotation: f32,
+        start: f32,
+        sweep: f32,
+    ) -> Vec<Self> {
+        let [cx, cy] = to_point(&center);
+        let (sin, cos) = (rotation as f64).sin_cos();
+        let (rx, ry) = (rx as f64, ry as f64);
+
+        // A point of the unit circle scaled by `scale`, mapped onto the ellipse
+        let place = |angle: f64, scale: f64| {
+            let (x, y) = (scale * rx * angle.cos(), scale * ry * angle.sin());
+            Vertex::new(
+                (cx + x * cos - y * sin) as f32,
+                (cy + x * sin + y * cos) as f32,
+            )
+        };
+
+        let (start, sweep) = (start as f64, sweep as f64);
+        let pieces = (sweep.abs() / std::f64::consts::FRAC_PI_2).ceil().max(1.) as usize;
+        let step = sweep / pieces as f64;
+        let weight = (step / 2.).cos();
+
+        (0..pieces)
+            .map(|i| {
+                let a0 = start + step * i as f64;
+                let a1 = a0 + step;
+                let points = [
+                    place(a0, 1.),
+                    place((a0 + a1) / 2., 1. / weight),
+                    place(a1, 1.),
+                ];
+                Self::new().with_weighted_points(&points, &[1., weight as f32, 1.])
+            })
+            .collect()
     }
 
-    fn single(&self, t: f32) -> Vertex {
-        let mut x_sum = 0.;
-        let mut y_sum = 0.;
-        let n = self.points.len() - 1;
+    pub fn push_point(&mut self, vertex: Vertex, weight: f32) {
+        self.points.push(vertex);
+        self.weights.push(weight);
+    }
 
-        for (k, v) in self.points.iter().enumerate() {
-            let poly = binomial(n, k) as f32
-                * (1. - t).powi((n - k).try_into().unwrap())
-                * t.powi(k.try_into().unwrap());
+    pub fn points(&self) -> &[Vertex] {
+        &self.points
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
 
-            x_sum += v.position[0] * poly;
-            y_sum += v.position[1] * poly;
+    fn control_points(&self) -> Vec<Homogeneous> {
+        self.points
+            .iter()
+            .zip(&self.weights)
+            .map(|(p, &w)| to_homogeneous(p, w))
+            .collect()
+    }
+
+    fn with_control_points(&self, points: &[Homogeneous]) -> Self {
+        Self {
+            steps: self.steps,
+            points: points.iter().map(|&p| to_vertex(project(p))).collect(),
+            weights: points.iter().map(|p| p[2] as f32).collect(),
+        }
+    }
+
+    // Point of the curve at `t`, None without points
+    pub fn single(&self, t: f32) -> Option<Vertex> {
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let mut scratch = Vec::with_capacity(self.points.len());
+        let point = de_casteljau(&mut scratch, &self.control_points(), t as f64);
+        Some(to_vertex(project(point)))
+    }
+
+    // Evenly spaced samples at t = i / steps, like `Bezier::samples`
+    pub fn samples(&self, steps: usize) -> Vec<Vertex> {
+        if steps == 0 || self.points.is_empty() {
+            return Vec::new();
+        }
+
+        let points = self.control_points();
+        let mut scratch = Vec::with_capacity(points.len());
+        (0..=steps)
+            .map(|i| {
+                let t = i as f64 / steps as f64;
+                to_vertex(project(de_casteljau(&mut scratch, &points, t)))
+            })
+            .collect()
+    }
+
+    pub fn split(&self, t: f32) -> (RationalBezier, RationalBezier) {
+        let (left, right) = split_points(&self.control_points(), t as f64);
+        (
+            self.with_control_points(&left),
+            self.with_control_points(&right),
+        )
+    }
+
+    pub fn segment(&self, t0: f32, t1: f32) -> RationalBezier {
+        let points = segment_points(&self.control_points(), t0 as f64, t1 as f64);
+        self.with_control_points(&points)
+    }
+
+    pub fn flatten(&self, tolerance: f32) -> Vec<Vertex> {
+        let points = self.control_points();
+        let mut out = Vec::new();
+        if let Some(&first) = points.first() {
+            out.push(project(first));
         }
 
-        Vertex::new(x_sum, y_sum)
+        if points.len() >= 2 {
+            let tolerance = tolerance.max(f32::EPSILON) as f64;
+            flatten_into(&points, project, tolerance, 16, &mut out);
+        }
+        out.into_iter().map(to_vertex).collect()
     }
 
     pub fn interpolate(&self) -> Option<Vec<Vertex>> {
-        if self.steps == 0 || self.points.len() < 3 {
+        if self.steps == 0 || self.points.len() < 2 {
             return None;
         }
 
-        let mut vertex = Vec::with_capacity(self.steps);
+        Some(self.samples(self.steps))
+    }
+}
+
+impl From<Bezier> for RationalBezier {
+    fn from(curve: Bezier) -> Self {
+        Self::new()
+            .with_steps(curve.steps)
+            .with_points(&curve.points)
+    }
+}
 
-        let inc = 1. / self.steps as f32;
-        let mut t = 0.;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        while t <= 1. {
-            vertex.push(self.single(t));
-            t += inc;
-        }
+    #[test]
+    fn reduce_to_a_point() {
+        let points: Vec<Vertex> = (0..12)
+            .map(|i| {
+                let a = i as f32;
+                Vertex::new(100. * (0.8 * a).cos(), 60. * (1.3 * a).sin() + a)
+            })
+            .collect();
+        let curve = Bezier::new().with_points(&points);
+
+        let reduced = curve.reduce_to(0);
+        assert_eq!(reduced.points().len(), 1);
 
-        Some(vertex)
+        // The mean of many evenly spaced samples tends to the mean point
+        let steps = 10000;
+        let [mut x, mut y] = [0., 0.];
+        for vertex in curve.samples(steps) {
+            x += vertex.position[0] as f64 / (steps + 1) as f64;
+            y += vertex.position[1] as f64 / (steps + 1) as f64;
+        }
+        let [rx, ry] = reduced.points()[0].position;
+        assert!((rx as f64 - x).abs() < 1e-2 && (ry as f64 - y).abs() < 1e-2);
     }
 }
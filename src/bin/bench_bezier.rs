@@ -0,0 +1,80 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use toryn::bezier::Bezier;
+use toryn::vertex::Vertex;
+
+// Runs `f` for at least half a second, returns the time per call
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let mut calls = 0;
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(500) {
+        for _ in 0..16 {
+            f();
+        }
+        calls += 16;
+    }
+    start.elapsed() / calls
+}
+
+// Largest distance between the samples of both paths
+fn largest_difference(a: &[Vertex], b: &[Vertex]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| {
+            let [x0, y0] = a.position;
+            let [x1, y1] = b.position;
+            (x1 - x0).hypot(y1 - y0)
+        })
+        .fold(0., f32::max)
+}
+
+fn main() {
+    let points = [
+        Vertex::new(20., 480.),
+        Vertex::new(120., 40.),
+        Vertex::new(380., 460.),
+        Vertex::new(480., 20.),
+    ];
+
+    println!(
+        "{:>6} {:>6} {:>12} {:>12} {:>12} {:>10}",
+        "degree", "steps", "single", "samples", "forward", "difference"
+    );
+
+    for degree in 1..=3 {
+        let curve = Bezier::new().with_points(&points[..=degree]);
+
+        for &steps in &[16, 256, 4096] {
+            let single = time(|| {
+                for i in 0..=steps {
+                    black_box(curve.single(i as f32 / steps as f32));
+                }
+            });
+            let samples = time(|| {
+                for vertex in curve.samples(steps) {
+                    black_box(vertex);
+                }
+            });
+            let forward = time(|| {
+                for vertex in curve.forward_samples(steps) {
+                    black_box(vertex);
+                }
+            });
+
+            let expected: Vec<Vertex> = curve.samples(steps).collect();
+            let actual: Vec<Vertex> = curve.forward_samples(steps).collect();
+
+            let per_sample = |duration: Duration| duration.as_nanos() as f64 / (steps + 1) as f64;
+            println!(
+                "{:>6} {:>6} {:>9.1} ns {:>9.1} ns {:>9.1} ns {:>10.2e}",
+                degree,
+                steps,
+                per_sample(single),
+                per_sample(samples),
+                per_sample(forward),
+                largest_difference(&expected, &actual)
+            );
+        }
+    }
+}
@@ -3,10 +3,12 @@ use glium::{
         self,
         event::{ElementState, Event, KeyboardInput, StartCause, VirtualKeyCode, WindowEvent},
     },
-    index::PrimitiveType,
+    implement_vertex,
+    index::{NoIndices, PrimitiveType},
     uniform,
     uniforms::{AsUniformValue, UniformValue},
-    Display, DrawParameters, IndexBuffer, Program, Rect, Surface, VertexBuffer,
+    Depth, DepthTest, Display, DrawParameters, IndexBuffer, PolygonMode, Program, Rect, Surface,
+    VertexBuffer,
 };
 
 use glm::{
@@ -17,7 +19,12 @@ use glm::{
 
 use lazy_static::lazy_static;
 use std::time::{Duration, Instant};
-use toryn::{create_window, primitives::vertex::Vertex3};
+use toryn::{
+    bezier::Bezier,
+    create_window,
+    primitives::vertex::Vertex3,
+    surface::{parse_patches, tessellate_patches, BezierPatch},
+};
 
 struct Settings {
     width: f64,
@@ -29,6 +36,9 @@ struct Settings {
     rot_y: f32,
     rot_z: f32,
     handle_motion: bool,
+    show_curve: bool,
+    wireframe: bool,
+    object: usize,
 }
 
 struct Camera {
@@ -49,13 +59,28 @@ struct Camera {
 
 struct Object {
     vertices: Vec<Vertex3>,
+    normals: Vec<Vertex3>,
     indices: Vec<u32>,
 }
 
+// Second vertex buffer of the objects, next to the positions
+#[derive(Clone, Copy)]
+struct Normal {
+    normal: [f32; 3],
+}
+
+implement_vertex!(Normal, normal);
+
+// Objects are shaded with their normals, the curve is drawn in plain lines
+struct Programs {
+    object: Program,
+    line: Program,
+}
+
 struct GlmMat4(Mat4);
 
 impl AsUniformValue for GlmMat4 {
-    fn as_uniform_value(&self) -> UniformValue {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
         let cols = self.0.as_array();
         let cols = [
             cols[0].as_array().to_owned(),
@@ -79,6 +104,9 @@ fn main() {
         rot_y: 0.0,
         rot_z: 0.0,
         handle_motion: false,
+        show_curve: true,
+        wireframe: false,
+        object: 0,
     };
 
     let mut camera = Camera {
@@ -123,20 +151,95 @@ fn main() {
                 color = vec4(1.0, 1.0, 1.0, 1.0);
             }
         "#;
+        static ref OBJECT_VERTEX_SHADER_SRC: &'static str = r#"
+            #version 330 core
+            in vec3 position;
+            in vec3 normal;
+
+            uniform mat4 model;
+            uniform mat4 view;
+            uniform mat4 projection;
+
+            out vec3 world_normal;
+
+            void main() {
+                // The model matrix only rotates
+                world_normal = mat3(model) * normal;
+                gl_Position = projection * view * model * vec4(position, 1.0);
+            }
+        "#;
+        static ref OBJECT_FRAGMENT_SHADER_SRC: &'static str = r#"
+            #version 330 core
+            in vec3 world_normal;
+            out vec4 color;
+
+            // Diffuse light from a fixed direction, on both sides of the
+            // surface
+            void main() {
+                vec3 light = normalize(vec3(0.3, 0.5, 1.0));
+                float diffuse = abs(dot(normalize(world_normal), light));
+                color = vec4(vec3(0.2 + 0.8 * diffuse), 1.0);
+            }
+        "#;
     }
 
-    let program =
-        Program::from_source(&display, *VERTEX_SHADER_SRC, *FRAGMENT_SHADER_SRC, None).unwrap();
+    let programs = Programs {
+        object: Program::from_source(
+            &display,
+            *OBJECT_VERTEX_SHADER_SRC,
+            *OBJECT_FRAGMENT_SHADER_SRC,
+            None,
+        )
+        .unwrap(),
+        line: Program::from_source(&display, *VERTEX_SHADER_SRC, *FRAGMENT_SHADER_SRC, None)
+            .unwrap(),
+    };
+
+    // Patches from the BPT file given as argument, like the Utah teapot, or a
+    // single bumpy one
+    let patches = match std::env::args().nth(1) {
+        Some(path) => {
+            let source = std::fs::read_to_string(&path).expect("Failed to read patches");
+            parse_patches(&source).expect("Failed to parse patches")
+        }
+        None => {
+            let heights = [
+                [0.0, 0.1, 0.1, 0.0],
+                [0.1, 0.3, -0.15, 0.05],
+                [0.0, -0.2, 0.25, 0.1],
+                [0.0, 0.05, 0.05, 0.0],
+            ];
+            let points: Vec<Vertex3> = (0..16)
+                .map(|k| {
+                    let (i, j) = (k / 4, k % 4);
+                    let (x, y) = (i as f32 / 3. - 0.5, j as f32 / 3. - 0.5);
+                    Vertex3::new(x, y, heights[i][j])
+                })
+                .collect();
+            vec![BezierPatch::bicubic(&points)]
+        }
+    };
 
-    let mut object = Object {
+    let quad = Object {
         vertices: vec![
             Vertex3::new(0.5, 0.5, 0.0),
             Vertex3::new(0.5, -0.5, 0.0),
             Vertex3::new(-0.5, -0.5, 0.0),
             Vertex3::new(-0.5, 0.5, 0.0),
         ],
+        normals: vec![Vertex3::new(0.0, 0.0, 1.0); 4],
         indices: vec![0_u32, 1, 3, 1, 2, 3],
     };
+    let mut objects = [quad, patch_object(&patches)];
+
+    // Winds out of the plane of the object and back into it
+    let curve = Bezier::new().with_points(&[
+        Vertex3::new(-0.5, -0.5, 0.0),
+        Vertex3::new(-0.5, 0.5, 0.8),
+        Vertex3::new(0.5, 0.5, -0.8),
+        Vertex3::new(0.5, -0.5, 0.8),
+        Vertex3::new(0.0, 0.0, 0.0),
+    ]);
 
     let draw_parameters = DrawParameters {
         viewport: Some(Rect {
@@ -161,17 +264,16 @@ fn main() {
                 }
 
                 WindowEvent::KeyboardInput { input, .. } => {
-                    handle_input(&input, &mut settings, &mut camera, &mut object)
+                    let current = settings.object;
+                    handle_input(&input, &mut settings, &mut camera, &mut objects[current])
                 }
 
                 WindowEvent::CursorEntered { .. } => {
                     settings.handle_motion = true;
                 }
 
-                WindowEvent::AxisMotion { axis, value, .. } => {
-                    if settings.handle_motion {
-                        handle_motion(axis, value, &mut camera)
-                    }
+                WindowEvent::AxisMotion { axis, value, .. } if settings.handle_motion => {
+                    handle_motion(axis, value, &mut camera)
                 }
                 _ => {}
             },
@@ -181,8 +283,9 @@ fn main() {
                     &camera,
                     &settings,
                     &display,
-                    &object,
-                    &program,
+                    &objects[settings.object],
+                    &curve,
+                    &programs,
                     &draw_parameters,
                 ),
 
@@ -200,11 +303,21 @@ fn draw(
     settings: &Settings,
     display: &Display,
     object: &Object,
-    program: &Program,
+    curve: &Bezier<Vertex3>,
+    programs: &Programs,
     draw_parameters: &DrawParameters,
 ) {
     let vertex_buffer =
         VertexBuffer::new(display, &object.vertices).expect("Failed to allocate vertex buffer");
+    let normals: Vec<Normal> = object
+        .normals
+        .iter()
+        .map(|normal| Normal {
+            normal: normal.position,
+        })
+        .collect();
+    let normal_buffer =
+        VertexBuffer::new(display, &normals).expect("Failed to allocate vertex buffer");
     let index_buffer = IndexBuffer::new(display, PrimitiveType::TrianglesList, &object.indices)
         .expect("Failed to allocate index buffer");
 
@@ -257,18 +370,77 @@ fn draw(
         projection: projection_matrix,
     };
 
+    let object_parameters = DrawParameters {
+        polygon_mode: if settings.wireframe {
+            PolygonMode::Line
+        } else {
+            PolygonMode::Fill
+        },
+        depth: Depth {
+            test: DepthTest::IfLess,
+            write: true,
+            ..Default::default()
+        },
+        ..draw_parameters.clone()
+    };
+
     // Draw!
     let mut frame = display.draw();
-    frame.clear_color(0., 0., 0., 1.);
+    frame.clear_color_and_depth((0., 0., 0., 1.), 1.);
     frame
         .draw(
-            &vertex_buffer,
+            (&vertex_buffer, &normal_buffer),
             &index_buffer,
-            program,
+            &programs.object,
             &uniforms,
-            draw_parameters,
+            &object_parameters,
         )
         .expect("Failed to draw");
+
+    if settings.show_curve {
+        // The curve within a hundredth of a unit, then its control polygon
+        // with the control points on top
+        let line_strip = NoIndices(PrimitiveType::LineStrip);
+        let points = NoIndices(PrimitiveType::Points);
+        let point_parameters = DrawParameters {
+            point_size: Some(5.),
+            ..draw_parameters.clone()
+        };
+
+        let flattened = VertexBuffer::new(display, &curve.flatten(0.01))
+            .expect("Failed to allocate vertex buffer");
+        let polygon =
+            VertexBuffer::new(display, curve.points()).expect("Failed to allocate vertex buffer");
+
+        frame
+            .draw(
+                &flattened,
+                line_strip,
+                &programs.line,
+                &uniforms,
+                draw_parameters,
+            )
+            .expect("Failed to draw");
+        frame
+            .draw(
+                &polygon,
+                line_strip,
+                &programs.line,
+                &uniforms,
+                draw_parameters,
+            )
+            .expect("Failed to draw");
+        frame
+            .draw(
+                &polygon,
+                points,
+                &programs.line,
+                &uniforms,
+                &point_parameters,
+            )
+            .expect("Failed to draw");
+    }
+
     frame.finish().expect("Failed to swap buffers");
 }
 
@@ -291,11 +463,7 @@ fn handle_motion(axis: u32, value: f64, camera: &mut Camera) {
         camera.axisx_value = value as f32;
     }
 
-    if camera.pitch > 89. {
-        camera.pitch = 89.;
-    } else if camera.pitch < -89. {
-        camera.pitch = -89.;
-    }
+    camera.pitch = camera.pitch.clamp(-89., 89.);
 
     let cos_pitch = camera.pitch.to_radians().cos();
     let sin_pitch = camera.pitch.to_radians().sin();
@@ -353,6 +521,18 @@ fn handle_input(
             camera.fov -= 1_f32.to_radians();
         }
 
+        (ElementState::Pressed, Some(VirtualKeyCode::C)) => {
+            settings.show_curve = !settings.show_curve;
+        }
+
+        (ElementState::Pressed, Some(VirtualKeyCode::P)) => {
+            settings.object = 1 - settings.object;
+        }
+
+        (ElementState::Pressed, Some(VirtualKeyCode::M)) => {
+            settings.wireframe = !settings.wireframe;
+        }
+
         (ElementState::Released, Some(VirtualKeyCode::Tab)) => {
             camera.use_perspective = !camera.use_perspective;
         }
@@ -397,6 +577,42 @@ fn handle_input(
     }
 }
 
+// Mesh of the patches within a five hundredth of their size, centered at the
+// origin and scaled to fit in a unit cube like the quad. The control points
+// bound the patches, so they give the size before tessellating.
+fn patch_object(patches: &[BezierPatch]) -> Object {
+    let (mut min, mut max) = ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]);
+    for vertex in patches.iter().flat_map(|patch| patch.points()) {
+        for k in 0..3 {
+            min[k] = min[k].min(vertex.position[k]);
+            max[k] = max[k].max(vertex.position[k]);
+        }
+    }
+
+    let extent = (0..3).map(|k| max[k] - min[k]).fold(f32::EPSILON, f32::max);
+    let center = |k: usize| (min[k] + max[k]) / 2.;
+    let mesh = tessellate_patches(patches, extent / 500.);
+
+    let vertices = mesh
+        .vertices
+        .iter()
+        .map(|vertex| {
+            let [x, y, z] = vertex.position;
+            Vertex3::new(
+                (x - center(0)) / extent,
+                (y - center(1)) / extent,
+                (z - center(2)) / extent,
+            )
+        })
+        .collect();
+
+    Object {
+        vertices,
+        normals: mesh.normals,
+        indices: mesh.indices,
+    }
+}
+
 fn ortho_matrix(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
     Mat4::new(
         Vec4::new(
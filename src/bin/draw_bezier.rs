@@ -1,8 +1,8 @@
 use glium::{
     glutin::{
         self,
-        dpi::LogicalPosition,
-        event::{ElementState, MouseButton},
+        dpi::PhysicalPosition,
+        event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode},
         event::{Event, StartCause, WindowEvent},
     },
     Surface,
@@ -12,8 +12,113 @@ use std::time::{Duration, Instant};
 
 use toryn::bezier::Bezier;
 use toryn::create_window;
+use toryn::fitting::fit_spline;
+use toryn::gpu::{GpuBezier, MAX_POINTS};
+use toryn::hull::monotone_chain;
+use toryn::spline::{Hermite, Parameterization, Spline};
+use toryn::stroke::{flatten_contour, Cap, Join, Stroke};
 use toryn::vertex::{draw_vertex_as_lines, draw_vertex_as_points, Vertex};
 
+// What the clicked points are turned into
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Bezier,
+    Spline,
+    CatmullRom,
+}
+
+// Open polyline, repeating the ends as adjacency for the line strip
+fn draw_polyline(line: &[Vertex], display: &glium::Display, frame: &mut glium::Frame) {
+    if line.len() >= 2 {
+        let mut strip = Vec::with_capacity(line.len() + 2);
+        strip.push(line[0]);
+        strip.extend_from_slice(line);
+        strip.push(line[line.len() - 1]);
+        draw_vertex_as_lines(&strip, display, frame);
+    }
+}
+
+// Index of the first point of the control polygon edge closest to `vertex`,
+// if it's within `radius`
+fn hit_edge(points: &[Vertex], vertex: Vertex, radius: f32) -> Option<usize> {
+    let [x, y] = vertex.position;
+    points
+        .windows(2)
+        .map(|edge| {
+            let [x0, y0] = edge[0].position;
+            let [x1, y1] = edge[1].position;
+            let (dx, dy) = (x1 - x0, y1 - y0);
+            let length2 = dx * dx + dy * dy;
+            let s = if length2 > 0. {
+                (((x - x0) * dx + (y - y0) * dy) / length2).clamp(0., 1.)
+            } else {
+                0.
+            };
+            (x0 + s * dx - x).hypot(y0 + s * dy - y)
+        })
+        .enumerate()
+        .filter(|&(_, distance)| distance <= radius)
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(i, _)| i)
+}
+
+// Control points before each edit that can be undone, and after each one
+// that can be redone. A grabbed point is kept aside until it's dragged, so
+// clicking it doesn't count as an edit.
+#[derive(Default)]
+struct History {
+    undo: Vec<Vec<Vertex>>,
+    redo: Vec<Vec<Vertex>>,
+    grabbed: Option<Vec<Vertex>>,
+}
+
+impl History {
+    // Called before changing the curve
+    fn record(&mut self, curve: &Bezier) {
+        self.undo.push(curve.points().to_vec());
+        self.redo.clear();
+    }
+
+    // Called when grabbing a point of the curve
+    fn grab(&mut self, curve: &Bezier) {
+        self.grabbed = Some(curve.points().to_vec());
+    }
+
+    // Called before moving the grabbed point, only the first move records it
+    fn drag(&mut self) {
+        if let Some(points) = self.grabbed.take() {
+            self.undo.push(points);
+            self.redo.clear();
+        }
+    }
+
+    fn release(&mut self) {
+        self.grabbed = None;
+    }
+
+    fn undo(&mut self, curve: &mut Bezier) -> bool {
+        match self.undo.pop() {
+            Some(points) => {
+                self.redo.push(curve.points().to_vec());
+                *curve = curve.clone().with_points(&points);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn redo(&mut self, curve: &mut Bezier) -> bool {
+        match self.redo.pop() {
+            Some(points) => {
+                self.undo.push(curve.points().to_vec());
+                *curve = curve.clone().with_points(&points);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
 fn main() {
     let (event_loop, display) = create_window!(
         title: "Draw bezier curves",
@@ -21,10 +126,41 @@ fn main() {
         height: 500.,
     );
 
-    let mut bezier_curve = Bezier::new().with_steps(100);
-    let mut points: Vec<Vertex> = Vec::new();
+    let mut bezier_curve = Bezier::new();
+
+    // Index of the control point following the cursor, and the edits made to
+    // the control points so far
+    let mut dragging: Option<usize> = None;
+    let mut history = History::default();
+    let mut edited = false;
+
+    let mut last_pos = PhysicalPosition::new(0., 0.);
+    let mut show_hull = false;
+    let mut show_frames = false;
+    let mut show_marks = false;
+    let mut show_cubics = false;
+    let mut show_bounds = false;
+    let mut mode = Mode::Bezier;
 
-    let mut last_pos = LogicalPosition::new(0., 0.);
+    // The bezier curve evaluated in the vertex shader instead, its control
+    // points are only uploaded when they're edited
+    let mut gpu_curve = GpuBezier::new(&display, 512);
+    let mut use_gpu = false;
+
+    // Parameter of the de Casteljau construction drawn over the curve, it
+    // sweeps the curve every 4 seconds unless paused and stepped by hand
+    let mut show_construction = false;
+    let mut construction_t = 0f32;
+    let mut animate = true;
+
+    // Caps and joins of the thick outline around the curve, if it's shown
+    let mut stroke_style: Option<(Cap, Join)> = None;
+
+    // Cursor positions of the stroke being drawn, and the curves fitted to
+    // the finished ones
+    let mut freehand = false;
+    let mut stroke: Option<Vec<Vertex>> = None;
+    let mut strokes: Vec<Spline> = Vec::new();
 
     event_loop.run(move |event, _, control_flow| {
         // 60 FPS
@@ -38,18 +174,281 @@ fn main() {
                     *control_flow = glutin::event_loop::ControlFlow::Exit;
                 }
 
-                WindowEvent::MouseInput { button, state, .. } => match (button, state) {
-                    (MouseButton::Left, ElementState::Pressed) => {
-                        let vertex = Vertex::new(last_pos.x as f32, last_pos.y as f32);
+                WindowEvent::MouseInput {
+                    button: MouseButton::Left,
+                    state: ElementState::Pressed,
+                    ..
+                } => {
+                    let vertex = Vertex::new(last_pos.x as f32, last_pos.y as f32);
+                    if freehand {
+                        stroke = Some(vec![vertex]);
+                    } else if let Some(i) = bezier_curve.hit_point(vertex, 8.) {
+                        history.grab(&bezier_curve);
+                        dragging = Some(i);
+                    } else if let Some(i) = hit_edge(bezier_curve.points(), vertex, 8.) {
+                        // On the control polygon, between the ends of the edge
+                        history.record(&bezier_curve);
+                        bezier_curve.insert_point(i + 1, vertex);
+                        dragging = Some(i + 1);
+                        edited = true;
+                    } else {
+                        history.record(&bezier_curve);
                         bezier_curve.push_point(vertex);
-                        points.push(vertex);
+                        edited = true;
+                    }
+                }
+
+                WindowEvent::MouseInput {
+                    button: MouseButton::Left,
+                    state: ElementState::Released,
+                    ..
+                } => {
+                    if dragging.take().is_some() {
+                        history.release();
                     }
 
-                    (_, _) => {}
-                },
+                    if let Some(samples) = stroke.take() {
+                        strokes.push(fit_spline(&samples, 2.));
+                    }
+                }
+
+                WindowEvent::MouseInput {
+                    button: MouseButton::Right,
+                    state: ElementState::Pressed,
+                    ..
+                } => {
+                    let vertex = Vertex::new(last_pos.x as f32, last_pos.y as f32);
+                    if dragging.is_none() {
+                        if let Some(i) = bezier_curve.hit_point(vertex, 8.) {
+                            history.record(&bezier_curve);
+                            bezier_curve.remove_point(i);
+                            edited = true;
+                        }
+                    }
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::X),
+                            ..
+                        },
+                    ..
+                } if dragging.is_none() && !bezier_curve.points().is_empty() => {
+                    history.record(&bezier_curve);
+                    bezier_curve.clear_points();
+                    edited = true;
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Z),
+                            ..
+                        },
+                    ..
+                } => {
+                    edited |= dragging.is_none() && history.undo(&mut bezier_curve);
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Y),
+                            ..
+                        },
+                    ..
+                } => {
+                    edited |= dragging.is_none() && history.redo(&mut bezier_curve);
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::B),
+                            ..
+                        },
+                    ..
+                } => {
+                    show_bounds = !show_bounds;
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::D),
+                            ..
+                        },
+                    ..
+                } => {
+                    show_construction = !show_construction;
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Space),
+                            ..
+                        },
+                    ..
+                } => {
+                    animate = !animate;
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Left),
+                            ..
+                        },
+                    ..
+                } => {
+                    animate = false;
+                    construction_t = (construction_t - 0.01).max(0.);
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Right),
+                            ..
+                        },
+                    ..
+                } => {
+                    animate = false;
+                    construction_t = (construction_t + 0.01).min(1.);
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::F),
+                            ..
+                        },
+                    ..
+                } => {
+                    freehand = !freehand;
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::G),
+                            ..
+                        },
+                    ..
+                } => {
+                    use_gpu = !use_gpu;
+                    let count = bezier_curve.points().len();
+                    if use_gpu && (2..=MAX_POINTS).contains(&count) {
+                        gpu_curve.set_curve(&bezier_curve);
+                    }
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::H),
+                            ..
+                        },
+                    ..
+                } => {
+                    show_hull = !show_hull;
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::N),
+                            ..
+                        },
+                    ..
+                } => {
+                    show_frames = !show_frames;
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::L),
+                            ..
+                        },
+                    ..
+                } => {
+                    show_marks = !show_marks;
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::C),
+                            ..
+                        },
+                    ..
+                } => {
+                    show_cubics = !show_cubics;
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::S),
+                            ..
+                        },
+                    ..
+                } => {
+                    mode = match mode {
+                        Mode::Bezier => Mode::Spline,
+                        Mode::Spline => Mode::CatmullRom,
+                        Mode::CatmullRom => Mode::Bezier,
+                    };
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::W),
+                            ..
+                        },
+                    ..
+                } => {
+                    stroke_style = match stroke_style {
+                        None => Some((Cap::Butt, Join::Bevel)),
+                        Some((Cap::Butt, _)) => Some((Cap::Square, Join::Miter)),
+                        Some((Cap::Square, _)) => Some((Cap::Round, Join::Round)),
+                        Some((Cap::Round, _)) => None,
+                    };
+                }
 
                 WindowEvent::CursorMoved { position, .. } => {
                     last_pos = position;
+                    if let Some(i) = dragging {
+                        let vertex = Vertex::new(position.x as f32, position.y as f32);
+                        if vertex.position != bezier_curve.points()[i].position {
+                            history.drag();
+                            bezier_curve.set_point(i, vertex);
+                            edited = true;
+                        }
+                    }
+                    if let Some(samples) = &mut stroke {
+                        samples.push(Vertex::new(position.x as f32, position.y as f32));
+                    }
                 }
                 _ => {}
             },
@@ -59,11 +458,183 @@ fn main() {
                     let mut frame = display.draw();
                     frame.clear_color(0., 0., 0., 1.);
 
-                    if let Some(vertex) = bezier_curve.interpolate() {
-                        draw_vertex_as_lines(&vertex, &display, &mut frame);
+                    let points = bezier_curve.points();
+                    if edited && points.len() <= MAX_POINTS {
+                        gpu_curve.set_curve(&bezier_curve);
+                    }
+                    edited = false;
+
+                    let construction =
+                        show_construction && mode == Mode::Bezier && points.len() >= 2;
+                    if construction && animate {
+                        construction_t = (construction_t + 1. / 240.) % 1.;
+                    }
+
+                    // Nothing to evaluate on the CPU when the shader does it
+                    let on_gpu = use_gpu
+                        && mode == Mode::Bezier
+                        && !show_cubics
+                        && !construction
+                        && points.len() <= MAX_POINTS;
+
+                    // Within half a pixel of the curve, only traced up to
+                    // the constructed point if there's one
+                    let mut curve = match mode {
+                        Mode::Bezier if construction => {
+                            bezier_curve.segment(0., construction_t).flatten(0.5)
+                        }
+                        Mode::Bezier if on_gpu => Vec::new(),
+                        Mode::Bezier => bezier_curve.flatten(0.5),
+                        Mode::Spline => Spline::through_points(points).flatten(0.5),
+                        Mode::CatmullRom => {
+                            Hermite::catmull_rom(points, Parameterization::Centripetal).flatten(0.5)
+                        }
+                    };
+
+                    let mut joints = Vec::new();
+                    if show_cubics && !construction && mode == Mode::Bezier && points.len() >= 2 {
+                        // The same curve as a chain of cubics within a pixel
+                        let cubics = bezier_curve.to_cubics(1.);
+                        curve = vec![points[0]];
+                        for cubic in &cubics {
+                            curve.extend_from_slice(&cubic.flatten(0.5)[1..]);
+                            joints.push(cubic.points()[0]);
+                        }
+                        joints.remove(0);
                     }
 
-                    draw_vertex_as_points(&points, &display, &mut frame);
+                    if on_gpu {
+                        gpu_curve.draw(&mut frame);
+                    } else {
+                        draw_polyline(&curve, &display, &mut frame);
+                    }
+
+                    if let Some((cap, join)) = stroke_style {
+                        // Outline of the curve drawn 30 pixels wide
+                        let path = match mode {
+                            Mode::Bezier => vec![bezier_curve.clone()],
+                            Mode::Spline => Spline::through_points(points).segments(),
+                            Mode::CatmullRom => {
+                                Hermite::catmull_rom(points, Parameterization::Centripetal)
+                                    .to_beziers()
+                            }
+                        };
+
+                        let stroke = Stroke::new(30.).with_cap(cap).with_join(join);
+                        for contour in stroke.outline(&path) {
+                            draw_polyline(&flatten_contour(&contour, 0.5), &display, &mut frame);
+                        }
+                    }
+
+                    // Points all in the same place have a hull of one point
+                    let hull = if show_hull && points.len() >= 3 {
+                        monotone_chain(points)
+                    } else {
+                        Vec::new()
+                    };
+                    if hull.len() >= 2 {
+                        // Closed loop, the first and last vertices are only
+                        // adjacency for the line strip
+                        let mut outline = Vec::with_capacity(hull.len() + 3);
+                        outline.push(hull[hull.len() - 1]);
+                        outline.extend_from_slice(&hull);
+                        outline.extend_from_slice(&hull[..2]);
+                        draw_vertex_as_lines(&outline, &display, &mut frame);
+                    }
+
+                    if show_frames && mode == Mode::Bezier && points.len() >= 2 {
+                        // Tangent and normal ticks along the curve, all in one
+                        // strip that goes back to the curve after each tick
+                        let mut ticks = Vec::new();
+                        for i in 0..=10 {
+                            let t = i as f32 / 10.;
+                            let [x, y] = bezier_curve.single(t).unwrap().position;
+                            let [tx, ty] = bezier_curve.tangent(t).position;
+                            let [nx, ny] = bezier_curve.normal(t).position;
+
+                            let origin = Vertex::new(x, y);
+                            ticks.push(origin);
+                            ticks.push(Vertex::new(x + 25. * tx, y + 25. * ty));
+                            ticks.push(origin);
+                            ticks.push(Vertex::new(x + 25. * nx, y + 25. * ny));
+                            ticks.push(origin);
+                        }
+
+                        ticks.insert(0, ticks[0]);
+                        ticks.push(ticks[ticks.len() - 1]);
+                        draw_vertex_as_lines(&ticks, &display, &mut frame);
+
+                        let inflections: Vec<Vertex> = bezier_curve
+                            .inflections()
+                            .into_iter()
+                            .filter_map(|t| bezier_curve.single(t))
+                            .collect();
+                        draw_vertex_as_points(&inflections, &display, &mut frame);
+                    }
+
+                    if show_marks && mode == Mode::Bezier && points.len() >= 2 {
+                        // Roughly every 20 pixels along the curve
+                        let steps = (bezier_curve.length() / 20.).ceil() as usize;
+                        let marks = bezier_curve.uniform_samples(steps);
+                        draw_vertex_as_points(&marks, &display, &mut frame);
+                    }
+
+                    if show_bounds && mode == Mode::Bezier {
+                        // Tight box around the curve, and its closest point
+                        // to the cursor
+                        if let Some((min, max)) = bezier_curve.bounding_box() {
+                            let ([x0, y0], [x1, y1]) = (min.position, max.position);
+                            let corners = [
+                                Vertex::new(x0, y1),
+                                Vertex::new(x0, y0),
+                                Vertex::new(x1, y0),
+                                Vertex::new(x1, y1),
+                                Vertex::new(x0, y1),
+                                Vertex::new(x0, y0),
+                            ];
+                            draw_polyline(&corners, &display, &mut frame);
+                        }
+
+                        let cursor = Vertex::new(last_pos.x as f32, last_pos.y as f32);
+                        if let Some((_, nearest)) = bezier_curve.nearest(cursor) {
+                            draw_vertex_as_points(&[nearest], &display, &mut frame);
+                        }
+                    }
+
+                    if construction {
+                        // Every level interpolates the one above it at t, down
+                        // to the point of the curve, boxed
+                        let levels = bezier_curve.de_casteljau_levels(construction_t);
+                        for level in &levels {
+                            draw_polyline(level, &display, &mut frame);
+                            draw_vertex_as_points(level, &display, &mut frame);
+                        }
+
+                        let [x, y] = levels[levels.len() - 1][0].position;
+                        let corners = [
+                            Vertex::new(x - 5., y + 5.),
+                            Vertex::new(x - 5., y - 5.),
+                            Vertex::new(x + 5., y - 5.),
+                            Vertex::new(x + 5., y + 5.),
+                            Vertex::new(x - 5., y + 5.),
+                            Vertex::new(x - 5., y - 5.),
+                        ];
+                        draw_polyline(&corners, &display, &mut frame);
+                    }
+
+                    if let Some(samples) = &stroke {
+                        draw_polyline(samples, &display, &mut frame);
+                    }
+
+                    for spline in &strokes {
+                        draw_polyline(&spline.flatten(0.5), &display, &mut frame);
+                        let anchors: Vec<Vertex> =
+                            spline.points().iter().step_by(3).copied().collect();
+                        draw_vertex_as_points(&anchors, &display, &mut frame);
+                    }
+
+                    draw_vertex_as_points(&joints, &display, &mut frame);
+                    draw_vertex_as_points(points, &display, &mut frame);
 
                     frame.finish().expect("Failed to swap buffers");
                 }
@@ -76,3 +647,42 @@ fn main() {
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edited_and_undone() -> (History, Bezier) {
+        let mut curve = Bezier::new().with_points(&[Vertex::new(0., 0.), Vertex::new(10., 0.)]);
+        let mut history = History::default();
+        history.record(&curve);
+        curve.push_point(Vertex::new(10., 10.));
+        assert!(history.undo(&mut curve));
+        (history, curve)
+    }
+
+    #[test]
+    fn click_without_drag_keeps_redo() {
+        let (mut history, mut curve) = edited_and_undone();
+        history.grab(&curve);
+        history.release();
+
+        assert!(history.redo(&mut curve));
+        assert_eq!(curve.points().len(), 3);
+    }
+
+    #[test]
+    fn drag_clears_redo() {
+        let (mut history, mut curve) = edited_and_undone();
+        history.grab(&curve);
+        history.drag();
+        curve.set_point(0, Vertex::new(5., 5.));
+        history.drag();
+        history.release();
+
+        assert!(!history.redo(&mut curve));
+        assert!(history.undo(&mut curve));
+        assert_eq!(curve.points()[0].position, [0., 0.]);
+        assert!(!history.undo(&mut curve));
+    }
+}
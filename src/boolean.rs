@@ -0,0 +1,579 @@
+use crate::math::{opposite, orient2d, strictly_between};
+use crate::points::Point2d;
+use crate::shapes::{Polygon2d, Shape2d};
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+type Point = [f64; 2];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BooleanOp {
+    Union,
+    Intersection,
+    Difference,
+    Xor,
+}
+
+impl BooleanOp {
+    fn contains(self, in_subject: bool, in_clip: bool) -> bool {
+        match self {
+            BooleanOp::Union => in_subject || in_clip,
+            BooleanOp::Intersection => in_subject && in_clip,
+            BooleanOp::Difference => in_subject && !in_clip,
+            BooleanOp::Xor => in_subject != in_clip,
+        }
+    }
+}
+
+// Inputs are sets of rings filled with the even-odd rule, so a multi-ring
+// polygon with holes is just all of its rings. The result is split into
+// polygons, each with its counter-clockwise outer ring and clockwise holes.
+pub fn compute(subject: &[Shape2d], clip: &[Shape2d], op: BooleanOp) -> Vec<Polygon2d> {
+    let mut graph = Graph::new(subject, clip);
+    graph.split_edges();
+
+    let edges = graph.result_edges(op);
+    let rings = graph.link_rings(&edges);
+    build_polygons(rings)
+}
+
+pub fn union(subject: &[Shape2d], clip: &[Shape2d]) -> Vec<Polygon2d> {
+    compute(subject, clip, BooleanOp::Union)
+}
+
+pub fn intersection(subject: &[Shape2d], clip: &[Shape2d]) -> Vec<Polygon2d> {
+    compute(subject, clip, BooleanOp::Intersection)
+}
+
+pub fn difference(subject: &[Shape2d], clip: &[Shape2d]) -> Vec<Polygon2d> {
+    compute(subject, clip, BooleanOp::Difference)
+}
+
+pub fn xor(subject: &[Shape2d], clip: &[Shape2d]) -> Vec<Polygon2d> {
+    compute(subject, clip, BooleanOp::Xor)
+}
+
+impl Shape2d {
+    pub fn union(&self, other: &Shape2d) -> Vec<Polygon2d> {
+        union(std::slice::from_ref(self), std::slice::from_ref(other))
+    }
+
+    pub fn intersection(&self, other: &Shape2d) -> Vec<Polygon2d> {
+        intersection(std::slice::from_ref(self), std::slice::from_ref(other))
+    }
+
+    pub fn difference(&self, other: &Shape2d) -> Vec<Polygon2d> {
+        difference(std::slice::from_ref(self), std::slice::from_ref(other))
+    }
+
+    pub fn xor(&self, other: &Shape2d) -> Vec<Polygon2d> {
+        xor(std::slice::from_ref(self), std::slice::from_ref(other))
+    }
+}
+
+fn signed_area(ring: &[Point]) -> f64 {
+    let mut area = 0.;
+    for i in 0..ring.len() {
+        let (p, q) = (ring[i], ring[(i + 1) % ring.len()]);
+        area += p[0] * q[1] - q[0] * p[1];
+    }
+    area / 2.
+}
+
+// Parity of the crossings between the edges and a ray cast from `p` towards +x,
+// or towards +y when `vertical` is set
+fn ray_parity<I>(p: Point, edges: I, vertical: bool) -> bool
+where
+    I: IntoIterator<Item = (Point, Point)>,
+{
+    let swap = |q: Point| if vertical { [q[1], q[0]] } else { q };
+    let p = swap(p);
+
+    let mut inside = false;
+    for (u, v) in edges {
+        let (u, v) = (swap(u), swap(v));
+        if (u[1] > p[1]) == (v[1] > p[1]) {
+            continue;
+        }
+
+        let side = orient2d(u, v, p);
+        if (v[1] > u[1] && side > 0.) || (v[1] < u[1] && side < 0.) {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+// Points closer than the tolerance are merged, so intersections computed from
+// different pairs of edges end up as the same vertex
+struct VertexPool {
+    points: Vec<Point>,
+    grid: HashMap<(i64, i64), Vec<usize>>,
+    tolerance: f64,
+}
+
+impl VertexPool {
+    fn new(tolerance: f64) -> Self {
+        Self {
+            points: Vec::new(),
+            grid: HashMap::new(),
+            tolerance,
+        }
+    }
+
+    fn cell(&self, p: Point) -> (i64, i64) {
+        (
+            (p[0] / self.tolerance).floor() as i64,
+            (p[1] / self.tolerance).floor() as i64,
+        )
+    }
+
+    fn insert(&mut self, p: Point) -> usize {
+        let (cx, cy) = self.cell(p);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if let Some(ids) = self.grid.get(&(cx + dx, cy + dy)) {
+                    for &id in ids {
+                        let q = self.points[id];
+                        let dist = (q[0] - p[0]).hypot(q[1] - p[1]);
+                        if dist <= self.tolerance {
+                            return id;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.points.push(p);
+        let id = self.points.len() - 1;
+        self.grid.entry((cx, cy)).or_default().push(id);
+        id
+    }
+}
+
+struct Segment {
+    beg: Point,
+    end: Point,
+    owner: usize,
+    splits: Vec<Point>,
+}
+
+struct Graph {
+    segments: Vec<Segment>,
+    pool: VertexPool,
+    // Split edges as (from, to, owner), in the direction of their input ring
+    edges: Vec<(usize, usize, usize)>,
+}
+
+impl Graph {
+    fn new(subject: &[Shape2d], clip: &[Shape2d]) -> Self {
+        let mut segments = Vec::new();
+        let mut scale: f64 = 1.;
+
+        for (owner, rings) in [subject, clip].iter().enumerate() {
+            for ring in rings.iter() {
+                let mut points: Vec<Point> = Vec::with_capacity(ring.points().len());
+                for p in ring.points() {
+                    let p = [p.x as f64, p.y as f64];
+                    if points.last() != Some(&p) {
+                        points.push(p);
+                    }
+                }
+
+                while points.len() > 1 && points.first() == points.last() {
+                    points.pop();
+                }
+
+                if points.len() < 3 || signed_area(&points) == 0. {
+                    continue;
+                }
+
+                for i in 0..points.len() {
+                    let (beg, end) = (points[i], points[(i + 1) % points.len()]);
+                    scale = scale.max(beg[0].abs()).max(beg[1].abs());
+                    segments.push(Segment {
+                        beg,
+                        end,
+                        owner,
+                        splits: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        Self {
+            segments,
+            pool: VertexPool::new(scale * 1e-9),
+            edges: Vec::new(),
+        }
+    }
+
+    // Splits every segment at its intersections with the others, including the
+    // endpoints of collinear overlaps, so that edges only meet at vertices and
+    // overlapping edges become identical
+    fn split_edges(&mut self) {
+        for i in 0..self.segments.len() {
+            for j in (i + 1)..self.segments.len() {
+                let (a, b) = (self.segments[i].beg, self.segments[i].end);
+                let (c, d) = (self.segments[j].beg, self.segments[j].end);
+
+                if a[0].max(b[0]) < c[0].min(d[0])
+                    || c[0].max(d[0]) < a[0].min(b[0])
+                    || a[1].max(b[1]) < c[1].min(d[1])
+                    || c[1].max(d[1]) < a[1].min(b[1])
+                {
+                    continue;
+                }
+
+                let d1 = orient2d(a, b, c);
+                let d2 = orient2d(a, b, d);
+                let d3 = orient2d(c, d, a);
+                let d4 = orient2d(c, d, b);
+
+                let mut split_i = Vec::new();
+                let mut split_j = Vec::new();
+
+                if d1 == 0. && d2 == 0. {
+                    split_i.extend([c, d].iter().filter(|&&p| strictly_between(a, b, p)));
+                    split_j.extend([a, b].iter().filter(|&&p| strictly_between(c, d, p)));
+                } else if opposite(d1, d2) && opposite(d3, d4) {
+                    let t = d3 / (d3 - d4);
+                    let p = [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])];
+                    split_i.push(p);
+                    split_j.push(p);
+                } else {
+                    if d1 == 0. && strictly_between(a, b, c) {
+                        split_i.push(c);
+                    }
+                    if d2 == 0. && strictly_between(a, b, d) {
+                        split_i.push(d);
+                    }
+                    if d3 == 0. && strictly_between(c, d, a) {
+                        split_j.push(a);
+                    }
+                    if d4 == 0. && strictly_between(c, d, b) {
+                        split_j.push(b);
+                    }
+                }
+
+                self.segments[i].splits.extend(split_i);
+                self.segments[j].splits.extend(split_j);
+            }
+        }
+
+        for segment in &mut self.segments {
+            let (beg, end) = (segment.beg, segment.end);
+            let dir = [end[0] - beg[0], end[1] - beg[1]];
+            let param = |p: &Point| (p[0] - beg[0]) * dir[0] + (p[1] - beg[1]) * dir[1];
+            segment
+                .splits
+                .sort_by(|p, q| param(p).partial_cmp(&param(q)).unwrap());
+
+            let mut from = self.pool.insert(beg);
+            for &p in segment.splits.iter().chain(std::iter::once(&end)) {
+                let to = self.pool.insert(p);
+                if to != from {
+                    self.edges.push((from, to, segment.owner));
+                    from = to;
+                }
+            }
+        }
+    }
+
+    // Keeps the edges that separate the inside of the result from its outside,
+    // oriented so the inside is on their left
+    fn result_edges(&self, op: BooleanOp) -> Vec<(usize, usize)> {
+        let mut unique: HashMap<(usize, usize), [usize; 2]> = HashMap::new();
+        for &(from, to, owner) in &self.edges {
+            let key = (from.min(to), from.max(to));
+            unique.entry(key).or_insert([0, 0])[owner] += 1;
+        }
+
+        let mut keys: Vec<_> = unique.keys().copied().collect();
+        keys.sort_unstable();
+
+        let mut result = Vec::new();
+        for (lo, hi) in keys {
+            let count = unique[&(lo, hi)];
+            let (p, q) = (self.pool.points[lo], self.pool.points[hi]);
+            let mid = [(p[0] + q[0]) / 2., (p[1] + q[1]) / 2.];
+
+            // A ray cast from just beside the edge crosses the same edges as one
+            // cast from its midpoint, plus the edge itself when the ray starts on
+            // the side facing it
+            let vertical = p[1] == q[1];
+            let mut left = [false; 2];
+            let mut right = [false; 2];
+
+            for owner in 0..2 {
+                let others = self
+                    .edges
+                    .iter()
+                    .filter(|&&(from, to, o)| {
+                        o == owner && (from.min(to), from.max(to)) != (lo, hi)
+                    })
+                    .map(|&(from, to, _)| (self.pool.points[from], self.pool.points[to]));
+
+                let far_side = ray_parity(mid, others, vertical);
+                let near_side = far_side ^ (count[owner] % 2 == 1);
+
+                // The ray points towards +x (or +y), so the side facing the edge
+                // is west (or south) of it
+                let left_is_near = if vertical { q[0] < p[0] } else { q[1] > p[1] };
+                if left_is_near {
+                    left[owner] = near_side;
+                    right[owner] = far_side;
+                } else {
+                    left[owner] = far_side;
+                    right[owner] = near_side;
+                }
+            }
+
+            let inside_left = op.contains(left[0], left[1]);
+            let inside_right = op.contains(right[0], right[1]);
+
+            if inside_left && !inside_right {
+                result.push((lo, hi));
+            } else if !inside_left && inside_right {
+                result.push((hi, lo));
+            }
+        }
+
+        result
+    }
+
+    // Links the oriented edges into closed rings. At shared vertices the next
+    // edge is the first one found turning clockwise from the incoming edge,
+    // which keeps rings touching at a single vertex apart
+    fn link_rings(&self, edges: &[(usize, usize)]) -> Vec<Vec<Point>> {
+        let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (id, &(from, _)) in edges.iter().enumerate() {
+            outgoing.entry(from).or_default().push(id);
+        }
+
+        let points = &self.pool.points;
+        let angle = |from: usize, to: usize| {
+            let (p, q) = (points[from], points[to]);
+            (q[1] - p[1]).atan2(q[0] - p[0])
+        };
+
+        let next_edge = |id: usize| {
+            let (from, to) = edges[id];
+            let back = angle(to, from);
+            outgoing[&to]
+                .iter()
+                .copied()
+                .map(|next| {
+                    let mut turn = back - angle(to, edges[next].1);
+                    while turn <= 0. {
+                        turn += 2. * PI;
+                    }
+                    (next, turn)
+                })
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+                .map(|(next, _)| next)
+                .unwrap()
+        };
+
+        let mut used = vec![false; edges.len()];
+        let mut rings = Vec::new();
+
+        for start in 0..edges.len() {
+            if used[start] {
+                continue;
+            }
+
+            let mut ring = Vec::new();
+            let mut current = start;
+            loop {
+                used[current] = true;
+                ring.push(points[edges[current].0]);
+                current = next_edge(current);
+                if current == start || used[current] {
+                    break;
+                }
+            }
+
+            rings.push(ring);
+        }
+
+        rings
+    }
+}
+
+fn round_ring(ring: &[Point]) -> Vec<Point2d> {
+    let mut points: Vec<Point2d> = Vec::with_capacity(ring.len());
+    for p in ring {
+        let p = Point2d::new(p[0].round() as i32, p[1].round() as i32);
+        if points.last() != Some(&p) {
+            points.push(p);
+        }
+    }
+
+    while points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    // Splitting leaves vertices in the middle of straight edges
+    let collinear = |a: &Point2d, b: &Point2d, c: &Point2d| {
+        let (ax, ay) = ((b.x - a.x) as i64, (b.y - a.y) as i64);
+        let (bx, by) = ((c.x - a.x) as i64, (c.y - a.y) as i64);
+        ax * by - ay * bx == 0
+    };
+
+    let mut changed = true;
+    while changed && points.len() >= 3 {
+        changed = false;
+        for i in 0..points.len() {
+            let len = points.len();
+            let (prev, next) = ((i + len - 1) % len, (i + 1) % len);
+            if collinear(&points[prev], &points[i], &points[next]) {
+                points.remove(i);
+                changed = true;
+                break;
+            }
+        }
+    }
+
+    points
+}
+
+fn build_polygons(rings: Vec<Vec<Point>>) -> Vec<Polygon2d> {
+    let (outers, holes): (Vec<_>, Vec<_>) = rings
+        .into_iter()
+        .map(|ring| (signed_area(&ring), ring))
+        .filter(|(area, _)| *area != 0.)
+        .partition(|(area, _)| *area > 0.);
+
+    let mut polygons: Vec<(Shape2d, Vec<Shape2d>)> = outers
+        .iter()
+        .map(|(_, ring)| (Shape2d::new(&round_ring(ring)), Vec::new()))
+        .collect();
+
+    for (_, hole) in &holes {
+        let (p, q) = (hole[0], hole[1]);
+        let mid = [(p[0] + q[0]) / 2., (p[1] + q[1]) / 2.];
+
+        let container = outers
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, outer))| {
+                let edges = (0..outer.len()).map(|i| (outer[i], outer[(i + 1) % outer.len()]));
+                ray_parity(mid, edges, false)
+            })
+            .min_by(|(_, (a, _)), (_, (b, _))| a.partial_cmp(b).unwrap())
+            .map(|(index, _)| index);
+
+        if let Some(index) = container {
+            polygons[index].1.push(Shape2d::new(&round_ring(hole)));
+        }
+    }
+
+    polygons
+        .into_iter()
+        .filter(|(outer, _)| outer.points().len() >= 3)
+        .map(|(outer, holes)| {
+            let holes = holes
+                .into_iter()
+                .filter(|h| h.points().len() >= 3)
+                .collect();
+            Polygon2d::new(outer, holes)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rectangle(x0: i32, y0: i32, x1: i32, y1: i32) -> Shape2d {
+        Shape2d::new(&[
+            Point2d::new(x0, y0),
+            Point2d::new(x1, y0),
+            Point2d::new(x1, y1),
+            Point2d::new(x0, y1),
+        ])
+    }
+
+    fn ring_area(ring: &Shape2d) -> f64 {
+        let points: Vec<Point> = ring
+            .points()
+            .iter()
+            .map(|p| [p.x as f64, p.y as f64])
+            .collect();
+        signed_area(&points).abs()
+    }
+
+    fn area(polygons: &[Polygon2d]) -> f64 {
+        polygons
+            .iter()
+            .map(|p| ring_area(p.outer()) - p.holes().iter().map(ring_area).sum::<f64>())
+            .sum()
+    }
+
+    // Checks union + intersection = A + B and A - B = A - (A ∩ B), returns
+    // the area of the intersection
+    fn check_identities(a: &Shape2d, b: &Shape2d) -> f64 {
+        let (area_a, area_b) = (ring_area(a), ring_area(b));
+        let union = area(&a.union(b));
+        let intersection = area(&a.intersection(b));
+        let difference = area(&a.difference(b));
+
+        assert!((union + intersection - (area_a + area_b)).abs() < 1e-9);
+        assert!((difference - (area_a - intersection)).abs() < 1e-9);
+        intersection
+    }
+
+    #[test]
+    fn shared_edge() {
+        let a = rectangle(0, 0, 10, 10);
+        let b = rectangle(10, 0, 20, 10);
+        assert_eq!(check_identities(&a, &b), 0.);
+        assert_eq!(area(&a.union(&b)), 200.);
+    }
+
+    #[test]
+    fn partially_shared_edge() {
+        let a = rectangle(0, 0, 10, 10);
+        let b = rectangle(10, 5, 20, 15);
+        assert_eq!(check_identities(&a, &b), 0.);
+        assert_eq!(area(&a.union(&b)), 200.);
+    }
+
+    #[test]
+    fn collinear_overlapping_edges() {
+        let a = rectangle(0, 0, 10, 10);
+        let b = rectangle(5, 0, 15, 10);
+        assert_eq!(check_identities(&a, &b), 50.);
+        assert_eq!(area(&a.xor(&b)), 100.);
+    }
+
+    #[test]
+    fn identical_polygons() {
+        let a = rectangle(0, 0, 10, 10);
+        assert_eq!(check_identities(&a, &a), 100.);
+        assert!(a.difference(&a).is_empty());
+        assert!(a.xor(&a).is_empty());
+    }
+
+    #[test]
+    fn touching_vertices() {
+        let a = rectangle(0, 0, 10, 10);
+        let b = rectangle(10, 10, 20, 20);
+        assert_eq!(check_identities(&a, &b), 0.);
+        assert_eq!(area(&a.union(&b)), 200.);
+    }
+
+    #[test]
+    fn hole_from_difference() {
+        let a = rectangle(0, 0, 30, 30);
+        let b = rectangle(10, 10, 20, 20);
+        assert_eq!(check_identities(&a, &b), 100.);
+
+        let difference = a.difference(&b);
+        assert_eq!(difference.len(), 1);
+        assert_eq!(difference[0].holes().len(), 1);
+    }
+}
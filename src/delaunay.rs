@@ -0,0 +1,388 @@
+use crate::hull::monotone_chain;
+use crate::math::{incircle, opposite, orient2d, strictly_between, Planar};
+use crate::points::Point2d;
+use crate::shapes::Shape2d;
+
+use std::collections::{HashMap, HashSet};
+
+type Point = [f64; 2];
+
+#[derive(Debug, Clone)]
+pub struct Triangulation {
+    points: Vec<Point>,
+    // Index of the first equal point for every input point
+    canonical: Vec<usize>,
+    // Counter-clockwise triangles, as indices into `points`
+    triangles: Vec<[usize; 3]>,
+    constraints: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Copy)]
+struct Site(usize, Point);
+
+impl Planar for Site {
+    fn xy(&self) -> [f64; 2] {
+        self.1
+    }
+}
+
+impl Triangulation {
+    // Bowyer-Watson incremental triangulation
+    pub fn new<P: Planar>(points: &[P]) -> Self {
+        Self::with_constraints(points, &[])
+    }
+
+    // Constrained Delaunay triangulation, the edges between the given pairs of
+    // points are always part of it. Constraints shouldn't cross each other.
+    pub fn with_constraints<P: Planar>(points: &[P], constraints: &[(usize, usize)]) -> Self {
+        let mut slf = Self {
+            points: points.iter().map(Planar::xy).collect(),
+            canonical: Vec::with_capacity(points.len()),
+            triangles: Vec::new(),
+            constraints: Vec::new(),
+        };
+
+        let mut seen: HashMap<(u64, u64), usize> = HashMap::new();
+        for (i, p) in slf.points.iter().enumerate() {
+            let key = (p[0].to_bits(), p[1].to_bits());
+            slf.canonical.push(*seen.entry(key).or_insert(i));
+        }
+
+        let n = slf.points.len();
+        let (min, max) = bounds(&slf.points);
+        let center = [(min[0] + max[0]) / 2., (min[1] + max[1]) / 2.];
+        let size = (max[0] - min[0]).max(max[1] - min[1]).max(1.) * 100.;
+
+        // Super triangle enclosing every point, removed at the end
+        slf.points.push([center[0] - 2. * size, center[1] - size]);
+        slf.points.push([center[0] + 2. * size, center[1] - size]);
+        slf.points.push([center[0], center[1] + 2. * size]);
+        slf.triangles.push([n, n + 1, n + 2]);
+
+        for i in 0..n {
+            if slf.canonical[i] == i {
+                slf.insert_point(i);
+            }
+        }
+
+        // The super triangle is finite, so points that are nearly collinear
+        // on the hull may not be joined yet
+        let sites: Vec<Site> = (0..n)
+            .filter(|&i| slf.canonical[i] == i)
+            .map(|i| Site(i, slf.points[i]))
+            .collect();
+        let hull = monotone_chain(&sites);
+        if hull.len() >= 3 {
+            for i in 0..hull.len() {
+                slf.insert_segment(hull[i].0, hull[(i + 1) % hull.len()].0);
+            }
+        }
+
+        slf.triangles.retain(|t| t.iter().all(|&v| v < n));
+        slf.points.truncate(n);
+
+        for &(a, b) in constraints {
+            slf.insert_constraint(a, b);
+        }
+
+        slf
+    }
+
+    pub fn points(&self) -> &[[f64; 2]] {
+        &self.points
+    }
+
+    pub fn triangles(&self) -> &[[usize; 3]] {
+        &self.triangles
+    }
+
+    pub fn constraints(&self) -> &[(usize, usize)] {
+        &self.constraints
+    }
+
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        let mut edges: Vec<(usize, usize)> = self
+            .triangles
+            .iter()
+            .flat_map(|t| vec![(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+            .map(|(u, v)| (u.min(v), u.max(v)))
+            .collect();
+
+        edges.sort_unstable();
+        edges.dedup();
+        edges
+    }
+
+    pub fn insert_constraint(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.canonical[a], self.canonical[b]);
+        if a != b {
+            self.insert_segment(a, b);
+            self.constraints.push((a, b));
+        }
+    }
+
+    fn insert_point(&mut self, p: usize) {
+        let point = self.points[p];
+        let (bad, good): (Vec<[usize; 3]>, Vec<[usize; 3]>) =
+            self.triangles.iter().partition(|t| {
+                incircle(
+                    self.points[t[0]],
+                    self.points[t[1]],
+                    self.points[t[2]],
+                    point,
+                ) > 0.
+            });
+
+        // The cavity left by the triangles whose circumcircle holds the point
+        // is star-shaped from it, so it's filled by joining its boundary to it
+        let edges: HashSet<(usize, usize)> = bad
+            .iter()
+            .flat_map(|t| vec![(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+            .collect();
+
+        self.triangles = good;
+        for &(u, v) in &edges {
+            if !edges.contains(&(v, u)) {
+                self.triangles.push([u, v, p]);
+            }
+        }
+    }
+
+    fn has_edge(&self, a: usize, b: usize) -> bool {
+        self.triangles
+            .iter()
+            .any(|t| t.contains(&a) && t.contains(&b))
+    }
+
+    // Forces the segment `a`-`b` into the triangulation, removing the
+    // triangles it crosses and filling the hole at each side of it again
+    fn insert_segment(&mut self, a: usize, b: usize) {
+        if a == b || self.has_edge(a, b) {
+            return;
+        }
+
+        let (pa, pb) = (self.points[a], self.points[b]);
+
+        // Vertices lying on the segment split it in two
+        let dist = |v: &usize| {
+            let p = self.points[*v];
+            (p[0] - pa[0]).hypot(p[1] - pa[1])
+        };
+        let on_segment = self
+            .triangles
+            .iter()
+            .flat_map(|t| t.iter().copied())
+            .filter(|&v| v != a && v != b)
+            .filter(|&v| {
+                let p = self.points[v];
+                orient2d(pa, pb, p) == 0. && strictly_between(pa, pb, p)
+            })
+            .min_by(|u, v| dist(u).partial_cmp(&dist(v)).unwrap());
+
+        if let Some(v) = on_segment {
+            self.insert_segment(a, v);
+            self.insert_segment(v, b);
+            return;
+        }
+
+        let crosses = |u: usize, v: usize| {
+            let (pu, pv) = (self.points[u], self.points[v]);
+            opposite(orient2d(pa, pb, pu), orient2d(pa, pb, pv))
+                && opposite(orient2d(pu, pv, pa), orient2d(pu, pv, pb))
+        };
+
+        let (crossed, kept): (Vec<[usize; 3]>, Vec<[usize; 3]>) = self
+            .triangles
+            .iter()
+            .partition(|t| crosses(t[0], t[1]) || crosses(t[1], t[2]) || crosses(t[2], t[0]));
+
+        if crossed.is_empty() {
+            return;
+        }
+
+        let edges: HashSet<(usize, usize)> = crossed
+            .iter()
+            .flat_map(|t| vec![(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+            .collect();
+
+        let next: HashMap<usize, usize> = edges
+            .iter()
+            .filter(|&&(u, v)| !edges.contains(&(v, u)))
+            .copied()
+            .collect();
+
+        // The boundary of the hole goes around from `a` to `b` along one side
+        // of the segment and back along the other
+        let chain = |from: usize, to: usize| {
+            let mut chain = vec![from];
+            while *chain.last().unwrap() != to {
+                chain.push(next[chain.last().unwrap()]);
+            }
+            chain
+        };
+
+        let first = chain(a, b);
+        let second = chain(b, a);
+
+        self.triangles = kept;
+        self.fill_pseudo_polygon(&first);
+        self.fill_pseudo_polygon(&second);
+    }
+
+    // Triangulates the polygon closed by the segment between the first and
+    // last vertices, choosing at each step the vertex whose circumcircle with
+    // that segment holds no other vertex
+    fn fill_pseudo_polygon(&mut self, polygon: &[usize]) {
+        if polygon.len() < 3 {
+            return;
+        }
+
+        let (e0, e1) = (polygon[0], polygon[polygon.len() - 1]);
+        let mut c = 1;
+        for j in 2..polygon.len() - 1 {
+            if self.in_circumcircle([e0, e1, polygon[c]], polygon[j]) {
+                c = j;
+            }
+        }
+
+        let mut triangle = [e0, e1, polygon[c]];
+        if orient2d(
+            self.points[triangle[0]],
+            self.points[triangle[1]],
+            self.points[triangle[2]],
+        ) < 0.
+        {
+            triangle.swap(0, 1);
+        }
+        self.triangles.push(triangle);
+
+        self.fill_pseudo_polygon(&polygon[..=c]);
+        self.fill_pseudo_polygon(&polygon[c..]);
+    }
+
+    fn in_circumcircle(&self, triangle: [usize; 3], v: usize) -> bool {
+        let [a, b, c] = triangle.map(|i| self.points[i]);
+        let d = self.points[v];
+        if orient2d(a, b, c) > 0. {
+            incircle(a, b, c, d) > 0.
+        } else {
+            incircle(b, a, c, d) > 0.
+        }
+    }
+
+    // One cell per input point, clipped to the box between `min` and `max`.
+    // Repeated points get an empty cell after the first one.
+    pub fn voronoi(&self, min: &Point2d, max: &Point2d) -> Vec<Shape2d> {
+        let clip_min = [min.x.min(max.x) as f64, min.y.min(max.y) as f64];
+        let clip_max = [min.x.max(max.x) as f64, min.y.max(max.y) as f64];
+
+        // Frame points far enough away to bound every cell without changing
+        // them inside the box
+        let (lo, hi) = bounds(&self.points);
+        let lo = [lo[0].min(clip_min[0]), lo[1].min(clip_min[1])];
+        let hi = [hi[0].max(clip_max[0]), hi[1].max(clip_max[1])];
+        let diag = (hi[0] - lo[0]).hypot(hi[1] - lo[1]).max(1.);
+        let margin = 3. * diag;
+
+        let mut sites = self.points.clone();
+        sites.push([lo[0] - margin, lo[1] - margin]);
+        sites.push([hi[0] + margin, lo[1] - margin]);
+        sites.push([hi[0] + margin, hi[1] + margin]);
+        sites.push([lo[0] - margin, hi[1] + margin]);
+
+        let framed = Triangulation::new(&sites);
+
+        let mut corners: Vec<Vec<Point>> = vec![Vec::new(); self.points.len()];
+        for t in &framed.triangles {
+            let center = circumcenter(t.map(|i| framed.points[i]));
+            for &v in t {
+                if v < self.points.len() {
+                    corners[v].push(center);
+                }
+            }
+        }
+
+        corners
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut cell)| {
+                let site = self.points[i];
+                let angle = |p: &Point| (p[1] - site[1]).atan2(p[0] - site[0]);
+                cell.sort_by(|p, q| angle(p).partial_cmp(&angle(q)).unwrap());
+
+                let cell = clip_to_box(&cell, clip_min, clip_max);
+                let mut points: Vec<Point2d> = Vec::with_capacity(cell.len());
+                for p in cell {
+                    let p = Point2d::new(p[0].round() as i32, p[1].round() as i32);
+                    if points.last() != Some(&p) && points.first() != Some(&p) {
+                        points.push(p);
+                    }
+                }
+
+                Shape2d::new(&points)
+            })
+            .collect()
+    }
+}
+
+fn bounds(points: &[Point]) -> (Point, Point) {
+    let mut min = [f64::INFINITY; 2];
+    let mut max = [f64::NEG_INFINITY; 2];
+    for p in points {
+        for axis in 0..2 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+
+    if points.is_empty() {
+        ([0.; 2], [0.; 2])
+    } else {
+        (min, max)
+    }
+}
+
+fn circumcenter([a, b, c]: [Point; 3]) -> Point {
+    let (bx, by) = (b[0] - a[0], b[1] - a[1]);
+    let (cx, cy) = (c[0] - a[0], c[1] - a[1]);
+    let d = 2. * (bx * cy - by * cx);
+    let (b2, c2) = (bx * bx + by * by, cx * cx + cy * cy);
+    [
+        a[0] + (cy * b2 - by * c2) / d,
+        a[1] + (bx * c2 - cx * b2) / d,
+    ]
+}
+
+// Sutherland-Hodgman clipping of a convex polygon against each side of the box
+fn clip_to_box(polygon: &[Point], min: Point, max: Point) -> Vec<Point> {
+    let mut output = polygon.to_vec();
+
+    for axis in 0..2 {
+        for &(bound, keep_below) in &[(min[axis], false), (max[axis], true)] {
+            let inside = |p: &Point| {
+                if keep_below {
+                    p[axis] <= bound
+                } else {
+                    p[axis] >= bound
+                }
+            };
+
+            let input = std::mem::take(&mut output);
+            for i in 0..input.len() {
+                let (p, q) = (input[i], input[(i + 1) % input.len()]);
+                if inside(&p) {
+                    output.push(p);
+                }
+
+                if inside(&p) != inside(&q) {
+                    let t = (bound - p[axis]) / (q[axis] - p[axis]);
+                    let mut cut = [p[0] + t * (q[0] - p[0]), p[1] + t * (q[1] - p[1])];
+                    cut[axis] = bound;
+                    output.push(cut);
+                }
+            }
+        }
+    }
+
+    output
+}
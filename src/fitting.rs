@@ -0,0 +1,231 @@
+use crate::bezier::{Bezier, ControlPoint};
+use crate::math::{add, dot, norm, scale, sub};
+use crate::spline::{Continuity, Spline};
+use crate::vertex::Vertex;
+
+type Point = [f64; 2];
+
+// Cubics are fitted in f64, and only turned into vertices at the end
+type Cubic = Bezier<Point>;
+
+fn to_cubic(points: [Point; 4]) -> Cubic {
+    Bezier::new().with_points(&points)
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    norm(sub(a, b))
+}
+
+fn normalize(a: Point) -> Point {
+    let length = norm(a);
+    if length == 0. {
+        a
+    } else {
+        scale(a, 1. / length)
+    }
+}
+
+// Weights of the control points of a cubic at `t`, the basis of the least
+// squares system for its handles
+fn bernstein(t: f64) -> [f64; 4] {
+    let s = 1. - t;
+    [s * s * s, 3. * s * s * t, 3. * s * t * t, t * t * t]
+}
+
+fn evaluate(curve: &Cubic, t: f64) -> Point {
+    curve.single(t as f32).unwrap()
+}
+
+// Parameters proportional to the distance along the polyline
+fn chord_length(points: &[Point]) -> Vec<f64> {
+    let mut u = vec![0.; points.len()];
+    for i in 1..points.len() {
+        u[i] = u[i - 1] + distance(points[i], points[i - 1]);
+    }
+
+    let total = u[u.len() - 1];
+    u.iter().map(|&d| d / total).collect()
+}
+
+// Handles along the given unit tangents, with the lengths that minimize the
+// squared distance from the points to the curve at their parameters. Falls
+// back to a third of the chord when the system is degenerate or gives a
+// handle pointing backwards.
+fn generate(points: &[Point], u: &[f64], start: Point, end: Point) -> Cubic {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let mut c = [[0.; 2]; 2];
+    let mut x = [0.; 2];
+
+    for (&p, &t) in points.iter().zip(u) {
+        let b = bernstein(t);
+        let a = [scale(start, b[1]), scale(end, b[2])];
+
+        c[0][0] += dot(a[0], a[0]);
+        c[0][1] += dot(a[0], a[1]);
+        c[1][1] += dot(a[1], a[1]);
+
+        let fixed = add(scale(first, b[0] + b[1]), scale(last, b[2] + b[3]));
+        let rest = sub(p, fixed);
+        x[0] += dot(a[0], rest);
+        x[1] += dot(a[1], rest);
+    }
+    c[1][0] = c[0][1];
+
+    let det = c[0][0] * c[1][1] - c[1][0] * c[0][1];
+    let (alpha_start, alpha_end) = if det.abs() > 1e-12 {
+        (
+            (x[0] * c[1][1] - x[1] * c[0][1]) / det,
+            (c[0][0] * x[1] - c[1][0] * x[0]) / det,
+        )
+    } else {
+        (0., 0.)
+    };
+
+    let chord = distance(first, last);
+    let epsilon = 1e-6 * chord;
+    let (alpha_start, alpha_end) = if alpha_start < epsilon || alpha_end < epsilon {
+        (chord / 3., chord / 3.)
+    } else {
+        (alpha_start, alpha_end)
+    };
+
+    to_cubic([
+        first,
+        add(first, scale(start, alpha_start)),
+        add(last, scale(end, alpha_end)),
+        last,
+    ])
+}
+
+// Largest squared distance from a point to the curve at its parameter, and
+// the index of that point
+fn max_error(points: &[Point], u: &[f64], cubic: &Cubic) -> (f64, usize) {
+    let mut worst = (0., points.len() / 2);
+    for i in 1..points.len() - 1 {
+        let d = sub(evaluate(cubic, u[i]), points[i]);
+        let error = dot(d, d);
+        if error >= worst.0 {
+            worst = (error, i);
+        }
+    }
+    worst
+}
+
+// One Newton step per point towards the parameter of its closest point on
+// the curve, with the derivatives evaluated from the hodographs
+fn reparameterize(points: &[Point], u: &[f64], cubic: &Cubic) -> Vec<f64> {
+    let derivative = cubic.derivative();
+    let second_derivative = derivative.derivative();
+    points
+        .iter()
+        .zip(u)
+        .map(|(&p, &t)| {
+            let d = sub(evaluate(cubic, t), p);
+            let (first, second) = (evaluate(&derivative, t), evaluate(&second_derivative, t));
+            let denominator = dot(first, first) + dot(d, second);
+            if denominator == 0. {
+                t
+            } else {
+                (t - dot(d, first) / denominator).clamp(0., 1.)
+            }
+        })
+        .collect()
+}
+
+fn fit_into(points: &[Point], start: Point, end: Point, tolerance: f64, out: &mut Vec<Cubic>) {
+    if points.len() == 2 {
+        let third = distance(points[0], points[1]) / 3.;
+        out.push(to_cubic([
+            points[0],
+            add(points[0], scale(start, third)),
+            add(points[1], scale(end, third)),
+            points[1],
+        ]));
+        return;
+    }
+
+    let mut u = chord_length(points);
+    let mut cubic = generate(points, &u, start, end);
+    let (mut error, mut split) = max_error(points, &u, &cubic);
+    if error <= tolerance {
+        out.push(cubic);
+        return;
+    }
+
+    // Close enough that better parameters might be all it takes
+    if error <= 4. * tolerance {
+        for _ in 0..4 {
+            u = reparameterize(points, &u, &cubic);
+            cubic = generate(points, &u, start, end);
+            let (e, s) = max_error(points, &u, &cubic);
+            error = e;
+            split = s;
+            if error <= tolerance {
+                out.push(cubic);
+                return;
+            }
+        }
+    }
+
+    // Split at the worst point, with a shared tangent there
+    let center = normalize(sub(points[split - 1], points[split + 1]));
+    fit_into(&points[..=split], start, center, tolerance, out);
+    fit_into(&points[split..], scale(center, -1.), end, tolerance, out);
+}
+
+fn fit(samples: &[Vertex], tolerance: f32) -> Vec<Cubic> {
+    let mut points: Vec<Point> = samples.iter().map(Vertex::coordinates).collect();
+    points.dedup();
+    if points.len() < 2 {
+        return Vec::new();
+    }
+
+    let n = points.len();
+    let start = normalize(sub(points[1], points[0]));
+    let end = normalize(sub(points[n - 2], points[n - 1]));
+
+    let mut out = Vec::new();
+    let tolerance = tolerance.max(f32::EPSILON) as f64;
+    fit_into(&points, start, end, tolerance * tolerance, &mut out);
+    out
+}
+
+fn to_vertices(cubic: &Cubic) -> Vec<Vertex> {
+    cubic
+        .points()
+        .iter()
+        .copied()
+        .map(Vertex::from_coordinates)
+        .collect()
+}
+
+// Chain of cubic curves through the first and last samples that stays within
+// `tolerance` of every sample, following Schneider's algorithm from Graphics
+// Gems: a least squares fit over chord length parameters, refined with Newton
+// steps, and split at the worst sample when that isn't enough. Needs at least
+// two distinct samples.
+pub fn fit_cubics(samples: &[Vertex], tolerance: f32) -> Vec<Bezier> {
+    fit(samples, tolerance)
+        .iter()
+        .map(|cubic| Bezier::new().with_points(&to_vertices(cubic)))
+        .collect()
+}
+
+// The same fit as a spline, whose joints are G1 since both sides of a split
+// share the tangent direction
+pub fn fit_spline(samples: &[Vertex], tolerance: f32) -> Spline {
+    let cubics = fit(samples, tolerance);
+    let mut spline = Spline::new();
+    if let Some(first) = cubics.first() {
+        spline.push_point(to_vertices(first)[0]);
+    }
+
+    for (i, cubic) in cubics.iter().enumerate() {
+        let points = to_vertices(cubic);
+        spline.push_segment(points[1], points[2], points[3]);
+        if i > 0 {
+            spline.set_continuity(i - 1, Continuity::G1);
+        }
+    }
+    spline
+}
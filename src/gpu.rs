@@ -0,0 +1,203 @@
+use crate::bezier::Bezier;
+use crate::math::{ortho, GMat4};
+use crate::vertex::Vertex;
+
+use glium::backend::Facade;
+use glium::index::{NoIndices, PrimitiveType};
+use glium::program::{ProgramCreationInput, TransformFeedbackMode};
+use glium::texture::Texture2d;
+use glium::uniforms::UniformBuffer;
+use glium::vertex::TransformFeedbackSession;
+use glium::{
+    framebuffer::SimpleFrameBuffer, implement_vertex, uniform, DrawParameters, Program, Surface,
+    VertexBuffer,
+};
+
+// Most control points a curve can have on the GPU, the size of the uniform
+// block holding them
+pub const MAX_POINTS: usize = 32;
+
+const VERTEX_SHADER_SRC: &str = r#"
+    #version 330 core
+    in float t;
+
+    // Only xy is used, vec4 keeps the std140 layout the same as the buffer's
+    layout(std140) uniform ControlPoints {
+        vec4 points[32];
+    };
+    uniform int count;
+    uniform mat4 proj;
+
+    out vec2 position;
+
+    // De Casteljau's algorithm as on the CPU, in f32 instead of f64
+    void main() {
+        vec2 scratch[32];
+        for (int i = 0; i < count; i++) {
+            scratch[i] = points[i].xy;
+        }
+        for (int level = 1; level < count; level++) {
+            for (int i = 0; i < count - level; i++) {
+                scratch[i] = scratch[i] * (1.0 - t) + scratch[i + 1] * t;
+            }
+        }
+
+        position = scratch[0];
+        gl_Position = proj * vec4(position, 0.0, 1.0);
+    }
+"#;
+
+const FRAGMENT_SHADER_SRC: &str = r#"
+    #version 330 core
+    out vec4 color;
+    void main() {
+        color = vec4(1.0, 1.0, 1.0, 1.0);
+    }
+"#;
+
+#[derive(Debug, Clone, Copy)]
+struct Parameter {
+    t: f32,
+}
+
+implement_vertex!(Parameter, t);
+
+// Bezier curve evaluated in the vertex shader. The values of `t` are uploaded
+// once and the control points only when they change, so drawing the curve
+// doesn't evaluate or upload anything on the CPU. Each vertex is the point at
+// its `t`, the samples of `Bezier::interpolate` with as many steps up to f32
+// rounding, within 1e-3 pixels for curves a thousand pixels across.
+pub struct GpuBezier {
+    program: Program,
+    parameters: VertexBuffer<Parameter>,
+    // The block has a single member, so the array alone matches it
+    points: UniformBuffer<[[f32; 4]; MAX_POINTS]>,
+    count: usize,
+}
+
+impl GpuBezier {
+    pub fn new<F: Facade>(facade: &F, steps: usize) -> Self {
+        let steps = steps.max(1);
+        let parameters: Vec<Parameter> = (0..=steps)
+            .map(|i| Parameter {
+                t: i as f32 / steps as f32,
+            })
+            .collect();
+
+        // The position is captured too, to read the samples back
+        let program = Program::new(
+            facade,
+            ProgramCreationInput::SourceCode {
+                vertex_shader: VERTEX_SHADER_SRC,
+                tessellation_control_shader: None,
+                tessellation_evaluation_shader: None,
+                geometry_shader: None,
+                fragment_shader: FRAGMENT_SHADER_SRC,
+                transform_feedback_varyings: Some((
+                    vec!["position".to_owned()],
+                    TransformFeedbackMode::Interleaved,
+                )),
+                outputs_srgb: false,
+                uses_point_size: false,
+            },
+        )
+        .expect("Failed to build bezier program");
+
+        Self {
+            program,
+            parameters: VertexBuffer::new(facade, &parameters)
+                .expect("Failed to allocate parameter buffer"),
+            points: UniformBuffer::new(facade, [[0.; 4]; MAX_POINTS])
+                .expect("Failed to allocate point buffer"),
+            count: 0,
+        }
+    }
+
+    pub fn steps(&self) -> usize {
+        self.parameters.len() - 1
+    }
+
+    pub fn set_points(&mut self, points: &[Vertex]) {
+        assert!(points.len() <= MAX_POINTS, "Too many points for the GPU");
+
+        let mut block = [[0.; 4]; MAX_POINTS];
+        for (slot, vertex) in block.iter_mut().zip(points) {
+            let [x, y] = vertex.position;
+            *slot = [x, y, 0., 0.];
+        }
+
+        self.points.write(&block);
+        self.count = points.len();
+    }
+
+    pub fn set_curve(&mut self, curve: &Bezier) {
+        self.set_points(curve.points());
+    }
+
+    // Line strip through the samples, in pixels like `draw_vertex_as_lines`
+    pub fn draw<S: Surface>(&self, surface: &mut S) {
+        if self.count < 2 {
+            return;
+        }
+
+        let (width, height) = surface.get_dimensions();
+        let proj = GMat4(ortho(0., width as f32, height as f32, 0.));
+        let uniforms = uniform! {
+            ControlPoints: &self.points,
+            count: self.count as i32,
+            proj: proj,
+        };
+
+        surface
+            .draw(
+                &self.parameters,
+                NoIndices(PrimitiveType::LineStrip),
+                &self.program,
+                &uniforms,
+                &Default::default(),
+            )
+            .expect("Failed to draw bezier curve");
+    }
+
+    // The samples as computed by the shader, read back through transform
+    // feedback. Nothing is drawn.
+    pub fn evaluate<F: Facade>(&self, facade: &F) -> Vec<Vertex> {
+        if self.count < 2 {
+            return Vec::new();
+        }
+
+        let mut samples: VertexBuffer<Vertex> = VertexBuffer::empty(facade, self.parameters.len())
+            .expect("Failed to allocate sample buffer");
+
+        {
+            let session = TransformFeedbackSession::new(facade, &self.program, &mut samples)
+                .expect("Transform feedback isn't supported");
+            let parameters = DrawParameters {
+                transform_feedback: Some(&session),
+                draw_primitives: false,
+                ..Default::default()
+            };
+
+            let target = Texture2d::empty(facade, 1, 1).expect("Failed to allocate texture");
+            let mut surface =
+                SimpleFrameBuffer::new(facade, &target).expect("Failed to build framebuffer");
+            let uniforms = uniform! {
+                ControlPoints: &self.points,
+                count: self.count as i32,
+                proj: GMat4(ortho(0., 1., 1., 0.)),
+            };
+
+            surface
+                .draw(
+                    &self.parameters,
+                    NoIndices(PrimitiveType::Points),
+                    &self.program,
+                    &uniforms,
+                    &parameters,
+                )
+                .expect("Failed to evaluate bezier curve");
+        }
+
+        samples.read().expect("Failed to read samples")
+    }
+}
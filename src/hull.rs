@@ -0,0 +1,206 @@
+use crate::bezier::ControlPoint;
+use crate::math::{cross3, dot, orient2d, orient3d, sub, Planar};
+use crate::points::Point2d;
+use crate::shapes::Shape2d;
+use crate::vertex::Vertex3;
+
+use std::collections::HashSet;
+
+// Both algorithms return the hull vertices in counter-clockwise order starting
+// from the leftmost point, skipping duplicated and collinear points
+pub fn monotone_chain<P: Planar + Clone>(points: &[P]) -> Vec<P> {
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&i, &j| points[i].xy().partial_cmp(&points[j].xy()).unwrap());
+    order.dedup_by(|i, j| points[*i].xy() == points[*j].xy());
+
+    if order.len() < 3 {
+        return order.iter().map(|&i| points[i].clone()).collect();
+    }
+
+    let mut hull: Vec<usize> = Vec::with_capacity(order.len() + 1);
+    let turns_left = |hull: &[usize], i: usize| {
+        let (a, b) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+        orient2d(points[a].xy(), points[b].xy(), points[i].xy()) > 0.
+    };
+
+    // Lower hull, left to right
+    for &i in &order {
+        while hull.len() >= 2 && !turns_left(&hull, i) {
+            hull.pop();
+        }
+        hull.push(i);
+    }
+
+    // Upper hull, right to left
+    let lower_len = hull.len() + 1;
+    for &i in order.iter().rev().skip(1) {
+        while hull.len() >= lower_len && !turns_left(&hull, i) {
+            hull.pop();
+        }
+        hull.push(i);
+    }
+
+    // The last point is the first one again
+    hull.pop();
+    hull.iter().map(|&i| points[i].clone()).collect()
+}
+
+pub fn quickhull<P: Planar + Clone>(points: &[P]) -> Vec<P> {
+    let by_xy = |i: &usize, j: &usize| points[*i].xy().partial_cmp(&points[*j].xy()).unwrap();
+    let (first, last) = match (
+        (0..points.len()).min_by(by_xy),
+        (0..points.len()).max_by(by_xy),
+    ) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Vec::new(),
+    };
+
+    if points[first].xy() == points[last].xy() {
+        return vec![points[first].clone()];
+    }
+
+    let all: Vec<usize> = (0..points.len()).collect();
+    let mut hull = vec![first];
+    expand(points, first, last, &all, &mut hull);
+    hull.push(last);
+    expand(points, last, first, &all, &mut hull);
+
+    hull.iter().map(|&i| points[i].clone()).collect()
+}
+
+// Pushes the hull vertices found strictly to the right of the edge `from -> to`,
+// in order
+fn expand<P: Planar>(points: &[P], from: usize, to: usize, set: &[usize], hull: &mut Vec<usize>) {
+    let (a, b) = (points[from].xy(), points[to].xy());
+    let outside: Vec<(usize, f64)> = set
+        .iter()
+        .map(|&i| (i, orient2d(a, b, points[i].xy())))
+        .filter(|&(_, side)| side < 0.)
+        .collect();
+
+    let farthest = outside
+        .iter()
+        .min_by(|x, y| x.1.partial_cmp(&y.1).unwrap())
+        .map(|&(i, _)| i);
+
+    if let Some(farthest) = farthest {
+        let outside: Vec<usize> = outside.iter().map(|&(i, _)| i).collect();
+        expand(points, from, farthest, &outside, hull);
+        hull.push(farthest);
+        expand(points, farthest, to, &outside, hull);
+    }
+}
+
+impl Shape2d {
+    pub fn convex_hull<P: Planar + Clone>(points: &[P]) -> Self {
+        let hull: Vec<Point2d> = monotone_chain(points)
+            .iter()
+            .map(|p| {
+                let [x, y] = p.xy();
+                Point2d::new(x.round() as i32, y.round() as i32)
+            })
+            .collect();
+
+        Self::new(&hull)
+    }
+}
+
+// Triangle mesh of a 3D hull, with faces wound counter-clockwise when seen
+// from outside
+#[derive(Debug, Clone)]
+pub struct Hull3d {
+    pub vertices: Vec<Vertex3>,
+    pub indices: Vec<u32>,
+}
+
+type Point3 = [f64; 3];
+
+// Incremental construction, returns `None` if all the points are coplanar
+pub fn convex_hull_3d(points: &[Vertex3]) -> Option<Hull3d> {
+    let pts: Vec<Point3> = points.iter().map(Vertex3::coordinates).collect();
+
+    let farthest = |score: &dyn Fn(Point3) -> f64| {
+        (0..pts.len()).max_by(|&i, &j| score(pts[i]).partial_cmp(&score(pts[j])).unwrap())
+    };
+
+    // Initial tetrahedron from extreme points
+    let a = 0;
+    let b = farthest(&|p| dot(sub(p, pts[a]), sub(p, pts[a])))?;
+    let c = farthest(&|p| {
+        let n = cross3(sub(pts[b], pts[a]), sub(p, pts[a]));
+        dot(n, n)
+    })?;
+    let d = farthest(&|p| orient3d(pts[a], pts[b], pts[c], p).abs())?;
+
+    // The predicate is exact, so only truly coplanar points give zero
+    if orient3d(pts[a], pts[b], pts[c], pts[d]) == 0. {
+        return None;
+    }
+
+    // Faces of the tetrahedron face away from the vertex opposite to them
+    let mut faces: Vec<[usize; 3]> = [[a, b, c, d], [a, b, d, c], [a, c, d, b], [b, c, d, a]]
+        .iter()
+        .map(|&[i, j, k, opposite]| {
+            if orient3d(pts[i], pts[j], pts[k], pts[opposite]) > 0. {
+                [i, k, j]
+            } else {
+                [i, j, k]
+            }
+        })
+        .collect();
+
+    for p in 0..pts.len() {
+        if [a, b, c, d].contains(&p) {
+            continue;
+        }
+
+        // Points on the plane of a face don't see it, a point outside the
+        // hull is strictly above at least one face
+        let visible: Vec<bool> = faces
+            .iter()
+            .map(|f| orient3d(pts[f[0]], pts[f[1]], pts[f[2]], pts[p]) > 0.)
+            .collect();
+
+        if !visible.contains(&true) {
+            continue;
+        }
+
+        // Edges of the visible region whose other face is hidden
+        let visible_edges: HashSet<(usize, usize)> = faces
+            .iter()
+            .zip(&visible)
+            .filter(|(_, &v)| v)
+            .flat_map(|(f, _)| vec![(f[0], f[1]), (f[1], f[2]), (f[2], f[0])])
+            .collect();
+
+        let horizon: Vec<(usize, usize)> = visible_edges
+            .iter()
+            .copied()
+            .filter(|&(u, v)| !visible_edges.contains(&(v, u)))
+            .collect();
+
+        let mut kept: Vec<[usize; 3]> = faces
+            .iter()
+            .zip(&visible)
+            .filter(|(_, &v)| !v)
+            .map(|(f, _)| *f)
+            .collect();
+
+        kept.extend(horizon.iter().map(|&(u, v)| [u, v, p]));
+        faces = kept;
+    }
+
+    let mut remap = vec![None; pts.len()];
+    let mut vertices = Vec::new();
+    let mut indices = Vec::with_capacity(faces.len() * 3);
+
+    for &i in faces.iter().flat_map(|f| f.iter()) {
+        let index = *remap[i].get_or_insert_with(|| {
+            vertices.push(points[i]);
+            vertices.len() as u32 - 1
+        });
+        indices.push(index);
+    }
+
+    Some(Hull3d { vertices, indices })
+}
@@ -0,0 +1,434 @@
+use crate::math::orient2d;
+use crate::points::Point2d;
+use crate::shapes::{Line2d, Shape2d};
+use crate::vertex::Vertex;
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub enum LineIntersection {
+    None,
+    Point(Vertex),
+    Overlap(Line2d),
+}
+
+fn orient(a: &Point2d, b: &Point2d, c: &Point2d) -> i128 {
+    let (abx, aby) = ((b.x - a.x) as i128, (b.y - a.y) as i128);
+    let (acx, acy) = ((c.x - a.x) as i128, (c.y - a.y) as i128);
+    abx * acy - aby * acx
+}
+
+fn on_segment(a: &Point2d, b: &Point2d, p: &Point2d) -> bool {
+    orient(a, b, p) == 0
+        && a.x.min(b.x) <= p.x
+        && p.x <= a.x.max(b.x)
+        && a.y.min(b.y) <= p.y
+        && p.y <= a.y.max(b.y)
+}
+
+fn to_vertex(p: &Point2d) -> Vertex {
+    Vertex::new(p.x as f32, p.y as f32)
+}
+
+impl Line2d {
+    // Exact for the integer endpoints, only a crossing point is rounded
+    pub fn intersection(&self, other: &Line2d) -> LineIntersection {
+        let (a, b) = (self.begin(), self.end());
+        let (c, d) = (other.begin(), other.end());
+
+        if a == b || c == d {
+            let (p, (u, v)) = if a == b { (a, (c, d)) } else { (c, (a, b)) };
+            return if on_segment(u, v, p) {
+                LineIntersection::Point(to_vertex(p))
+            } else {
+                LineIntersection::None
+            };
+        }
+
+        let d1 = orient(a, b, c);
+        let d2 = orient(a, b, d);
+
+        if d1 == 0 && d2 == 0 {
+            // Collinear, compare positions along the dominant axis
+            let along_x = (b.x - a.x).abs() >= (b.y - a.y).abs();
+            let key = |p: &Point2d| if along_x { (p.x, p.y) } else { (p.y, p.x) };
+            let sorted = |p: &'_ Point2d, q: &'_ Point2d| {
+                if key(p) <= key(q) {
+                    (p.clone(), q.clone())
+                } else {
+                    (q.clone(), p.clone())
+                }
+            };
+
+            let (lo1, hi1) = sorted(a, b);
+            let (lo2, hi2) = sorted(c, d);
+            let start = if key(&lo1) >= key(&lo2) { lo1 } else { lo2 };
+            let end = if key(&hi1) <= key(&hi2) { hi1 } else { hi2 };
+
+            return match key(&start).cmp(&key(&end)) {
+                Ordering::Greater => LineIntersection::None,
+                Ordering::Equal => LineIntersection::Point(to_vertex(&start)),
+                Ordering::Less => LineIntersection::Overlap(Line2d::new(start, end)),
+            };
+        }
+
+        let d3 = orient(c, d, a);
+        let d4 = orient(c, d, b);
+
+        if d1.signum() * d2.signum() > 0 || d3.signum() * d4.signum() > 0 {
+            return LineIntersection::None;
+        }
+
+        let t = d3 as f64 / (d3 - d4) as f64;
+        let x = a.x as f64 + t * (b.x - a.x) as f64;
+        let y = a.y as f64 + t * (b.y - a.y) as f64;
+        LineIntersection::Point(Vertex::new(x as f32, y as f32))
+    }
+}
+
+// A point where two or more segments meet, with the indices of all of them
+#[derive(Debug, Clone)]
+pub struct Crossing {
+    pub point: Vertex,
+    pub segments: Vec<usize>,
+}
+
+type Point = [f64; 2];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct EventPoint(Point);
+
+impl Eq for EventPoint {}
+
+impl PartialOrd for EventPoint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EventPoint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap()
+    }
+}
+
+struct Segment {
+    left: Point,
+    right: Point,
+}
+
+impl Segment {
+    fn is_vertical(&self) -> bool {
+        self.left[0] == self.right[0]
+    }
+
+    // Height of the segment over the sweep line, a vertical segment sits at
+    // the event being handled
+    fn y_at(&self, event: Point) -> f64 {
+        if self.is_vertical() {
+            return event[1].max(self.left[1]).min(self.right[1]);
+        }
+
+        let t = (event[0] - self.left[0]) / (self.right[0] - self.left[0]);
+        self.left[1] + t * (self.right[1] - self.left[1])
+    }
+
+    // Order right after the sweep line passes a point shared by both
+    fn cmp_slope(&self, other: &Segment) -> Ordering {
+        match (self.is_vertical(), other.is_vertical()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => {
+                let (dx1, dy1) = (self.right[0] - self.left[0], self.right[1] - self.left[1]);
+                let (dx2, dy2) = (
+                    other.right[0] - other.left[0],
+                    other.right[1] - other.left[1],
+                );
+                (dy1 * dx2).partial_cmp(&(dy2 * dx1)).unwrap()
+            }
+        }
+    }
+}
+
+// Treap ordered by the position of the segments along the sweep line. The
+// order changes as the sweep moves, so it's only ever searched by splitting on
+// predicates that are monotone at the current event.
+struct Status {
+    nodes: Vec<Node>,
+    seed: u64,
+}
+
+struct Node {
+    segment: usize,
+    priority: u64,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+impl Status {
+    fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    fn node(&mut self, segment: usize) -> Option<usize> {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+
+        self.nodes.push(Node {
+            segment,
+            priority: self.seed,
+            left: None,
+            right: None,
+        });
+        Some(self.nodes.len() - 1)
+    }
+
+    // Splits the tree in the prefix for which `goes_left` holds and the rest
+    fn split<F>(&mut self, root: Option<usize>, goes_left: &F) -> (Option<usize>, Option<usize>)
+    where
+        F: Fn(usize) -> bool,
+    {
+        let root = match root {
+            Some(root) => root,
+            None => return (None, None),
+        };
+
+        if goes_left(self.nodes[root].segment) {
+            let (mid, right) = self.split(self.nodes[root].right, goes_left);
+            self.nodes[root].right = mid;
+            (Some(root), right)
+        } else {
+            let (left, mid) = self.split(self.nodes[root].left, goes_left);
+            self.nodes[root].left = mid;
+            (left, Some(root))
+        }
+    }
+
+    fn merge(&mut self, left: Option<usize>, right: Option<usize>) -> Option<usize> {
+        match (left, right) {
+            (None, tree) | (tree, None) => tree,
+            (Some(l), Some(r)) => {
+                if self.nodes[l].priority > self.nodes[r].priority {
+                    self.nodes[l].right = self.merge(self.nodes[l].right, Some(r));
+                    Some(l)
+                } else {
+                    self.nodes[r].left = self.merge(Some(l), self.nodes[r].left);
+                    Some(r)
+                }
+            }
+        }
+    }
+
+    fn first(&self, mut root: Option<usize>) -> Option<usize> {
+        let mut segment = None;
+        while let Some(node) = root {
+            segment = Some(self.nodes[node].segment);
+            root = self.nodes[node].left;
+        }
+        segment
+    }
+
+    fn last(&self, mut root: Option<usize>) -> Option<usize> {
+        let mut segment = None;
+        while let Some(node) = root {
+            segment = Some(self.nodes[node].segment);
+            root = self.nodes[node].right;
+        }
+        segment
+    }
+
+    fn collect(&self, root: Option<usize>, out: &mut Vec<usize>) {
+        if let Some(node) = root {
+            self.collect(self.nodes[node].left, out);
+            out.push(self.nodes[node].segment);
+            self.collect(self.nodes[node].right, out);
+        }
+    }
+}
+
+struct Sweep {
+    segments: Vec<Segment>,
+    events: BTreeMap<EventPoint, Vec<usize>>,
+    tolerance: f64,
+}
+
+impl Sweep {
+    fn same_point(&self, p: Point, q: Point) -> bool {
+        (p[0] - q[0]).abs() <= self.tolerance && (p[1] - q[1]).abs() <= self.tolerance
+    }
+
+    fn contains(&self, segment: usize, p: Point) -> bool {
+        (self.segments[segment].y_at(p) - p[1]).abs() <= self.tolerance
+    }
+
+    // Queues the crossing of both segments if the sweep hasn't passed it yet,
+    // merging it with an already queued event at the same place
+    fn push_crossing(&mut self, s: usize, t: usize, current: Point) {
+        let (a, b) = (self.segments[s].left, self.segments[s].right);
+        let (c, d) = (self.segments[t].left, self.segments[t].right);
+
+        let (d1, d2) = (orient2d(a, b, c), orient2d(a, b, d));
+        let (d3, d4) = (orient2d(c, d, a), orient2d(c, d, b));
+
+        // Collinear overlaps start at an endpoint, which is already an event
+        let same_side = |u: f64, v: f64| (u > 0. && v > 0.) || (u < 0. && v < 0.);
+        if same_side(d1, d2) || same_side(d3, d4) || (d1 == 0. && d2 == 0.) {
+            return;
+        }
+
+        let t = d3 / (d3 - d4);
+        let p = [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])];
+
+        if self.same_point(p, current) || EventPoint(p) < EventPoint(current) {
+            return;
+        }
+
+        let lo = EventPoint([p[0] - self.tolerance, f64::NEG_INFINITY]);
+        let hi = EventPoint([p[0] + self.tolerance, f64::INFINITY]);
+        let queued = self
+            .events
+            .range(lo..=hi)
+            .map(|(key, _)| *key)
+            .find(|key| self.same_point(key.0, p));
+
+        if queued.is_none() {
+            self.events.insert(EventPoint(p), Vec::new());
+        }
+    }
+}
+
+// Bentley-Ottmann sweep, reports every point shared by two or more segments
+// in O((n + k) log n), ordered from left to right
+pub fn sweep_intersections(lines: &[Line2d]) -> Vec<Crossing> {
+    let mut sweep = Sweep {
+        segments: Vec::with_capacity(lines.len()),
+        events: BTreeMap::new(),
+        tolerance: 0.,
+    };
+
+    let mut scale: f64 = 1.;
+    for (index, line) in lines.iter().enumerate() {
+        let p = [line.begin().x as f64, line.begin().y as f64];
+        let q = [line.end().x as f64, line.end().y as f64];
+        let (left, right) = if EventPoint(p) <= EventPoint(q) {
+            (p, q)
+        } else {
+            (q, p)
+        };
+
+        scale = scale.max(p[0].abs()).max(p[1].abs());
+        scale = scale.max(q[0].abs()).max(q[1].abs());
+
+        sweep.segments.push(Segment { left, right });
+        sweep
+            .events
+            .entry(EventPoint(left))
+            .or_default()
+            .push(index);
+        sweep.events.entry(EventPoint(right)).or_default();
+    }
+    sweep.tolerance = scale * 1e-9;
+
+    let mut status = Status::new();
+    let mut root = None;
+    let mut crossings = Vec::new();
+
+    while let Some((EventPoint(p), upper)) = sweep.events.pop_first() {
+        let (below, rest) = status.split(root, &|s| {
+            sweep.segments[s].y_at(p) < p[1] - sweep.tolerance
+        });
+        let (through, above) = status.split(rest, &|s| sweep.contains(s, p));
+
+        let mut passing = Vec::new();
+        status.collect(through, &mut passing);
+
+        let mut involved: Vec<usize> = upper.iter().chain(&passing).copied().collect();
+        if involved.len() > 1 {
+            involved.sort_unstable();
+            crossings.push(Crossing {
+                point: Vertex::new(p[0] as f32, p[1] as f32),
+                segments: involved,
+            });
+        }
+
+        // Segments ending here leave the status, the ones starting or passing
+        // through are reinserted in their order after the event
+        let mut next: Vec<usize> = upper
+            .iter()
+            .chain(&passing)
+            .copied()
+            .filter(|&s| !sweep.same_point(sweep.segments[s].right, p))
+            .collect();
+        next.sort_by(|&s, &t| sweep.segments[s].cmp_slope(&sweep.segments[t]));
+
+        let mut middle = None;
+        for &s in &next {
+            let node = status.node(s);
+            middle = status.merge(middle, node);
+        }
+
+        let lower = status.last(below);
+        let upper = status.first(above);
+
+        match (status.first(middle), status.last(middle)) {
+            (Some(first), Some(last)) => {
+                if let Some(lower) = lower {
+                    sweep.push_crossing(lower, first, p);
+                }
+                if let Some(upper) = upper {
+                    sweep.push_crossing(last, upper, p);
+                }
+            }
+            _ => {
+                if let (Some(lower), Some(upper)) = (lower, upper) {
+                    sweep.push_crossing(lower, upper, p);
+                }
+            }
+        }
+
+        let merged = status.merge(below, middle);
+        root = status.merge(merged, above);
+    }
+
+    crossings
+}
+
+impl Shape2d {
+    // Crossings between the edges of the outline, leaving out the vertices
+    // shared by consecutive edges
+    pub fn self_intersections(&self) -> Vec<Crossing> {
+        let points = self.points();
+        let n = points.len();
+        if n < 3 {
+            return Vec::new();
+        }
+
+        let edges: Vec<Line2d> = (0..n)
+            .map(|i| Line2d::new(points[i].clone(), points[(i + 1) % n].clone()))
+            .collect();
+
+        sweep_intersections(&edges)
+            .into_iter()
+            .filter(|crossing| {
+                if let [s, t] = crossing.segments[..] {
+                    let adjacent = t == s + 1 || (s == 0 && t == n - 1);
+                    let shared = if t == s + 1 { t } else { s };
+                    let vertex = to_vertex(&points[shared]);
+                    !(adjacent && vertex.position == crossing.point.position)
+                } else {
+                    true
+                }
+            })
+            .collect()
+    }
+
+    pub fn is_simple(&self) -> bool {
+        self.points().len() >= 3 && self.self_intersections().is_empty()
+    }
+}
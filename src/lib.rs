@@ -8,4 +8,14 @@ pub use primitives::shapes;
 pub use primitives::vertex;
 
 pub mod bezier;
+pub mod boolean;
+pub mod delaunay;
+pub mod fitting;
+pub mod gpu;
+pub mod hull;
+pub mod intersection;
 pub mod math;
+pub mod nurbs;
+pub mod spline;
+pub mod stroke;
+pub mod surface;
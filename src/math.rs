@@ -1,10 +1,14 @@
+use crate::points::Point2d;
+use crate::vertex::Vertex;
+
 use glium::uniforms::{AsUniformValue, UniformValue};
 use glm::{Mat4, Vec4};
+use std::fmt::Debug;
 
 pub struct GMat4(pub Mat4);
 
 impl AsUniformValue for GMat4 {
-    fn as_uniform_value(&self) -> UniformValue {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
         let cols = self.0.as_array();
         let cols = [
             cols[0].as_array().to_owned(),
@@ -30,3 +34,318 @@ pub fn ortho(left: f32, rigth: f32, bottom: f32, top: f32) -> Mat4 {
         ),
     )
 }
+
+// Coordinates of a point in any dimension, the curves do all their math on
+// these as f64 and only convert back to their points at the end
+pub trait Coordinates: Copy + Debug + PartialEq + AsRef<[f64]> + AsMut<[f64]> {
+    const ZERO: Self;
+}
+
+impl<const N: usize> Coordinates for [f64; N] {
+    const ZERO: Self = [0.; N];
+}
+
+// Arithmetic on coordinates, coordinate by coordinate
+
+pub fn lerp<P: Coordinates>(a: P, b: P, t: f64) -> P {
+    let mut out = a;
+    for (x, y) in out.as_mut().iter_mut().zip(b.as_ref()) {
+        *x = (1. - t) * *x + t * y;
+    }
+    out
+}
+
+pub fn sub<P: Coordinates>(a: P, b: P) -> P {
+    let mut out = a;
+    for (x, y) in out.as_mut().iter_mut().zip(b.as_ref()) {
+        *x -= y;
+    }
+    out
+}
+
+pub fn add<P: Coordinates>(a: P, b: P) -> P {
+    let mut out = a;
+    for (x, y) in out.as_mut().iter_mut().zip(b.as_ref()) {
+        *x += y;
+    }
+    out
+}
+
+pub fn scale<P: Coordinates>(a: P, s: f64) -> P {
+    let mut out = a;
+    for x in out.as_mut() {
+        *x *= s;
+    }
+    out
+}
+
+pub fn dot<P: Coordinates>(a: P, b: P) -> f64 {
+    a.as_ref().iter().zip(b.as_ref()).map(|(x, y)| x * y).sum()
+}
+
+pub fn norm<P: Coordinates>(a: P) -> f64 {
+    dot(a, a).sqrt()
+}
+
+// The z of the cross product of two vectors in the plane
+pub fn cross(a: [f64; 2], b: [f64; 2]) -> f64 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+pub fn cross3(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+// Anything that can be read as a point in the plane
+pub trait Planar {
+    fn xy(&self) -> [f64; 2];
+}
+
+impl Planar for [f64; 2] {
+    fn xy(&self) -> [f64; 2] {
+        *self
+    }
+}
+
+impl Planar for Point2d {
+    fn xy(&self) -> [f64; 2] {
+        [self.x as f64, self.y as f64]
+    }
+}
+
+impl Planar for Vertex {
+    fn xy(&self) -> [f64; 2] {
+        [self.position[0] as f64, self.position[1] as f64]
+    }
+}
+
+// Adaptive precision predicates after Shewchuk, "Adaptive Precision
+// Floating-Point Arithmetic and Fast Robust Geometric Predicates". The plain
+// floating point result is used when it's provably correct, otherwise the
+// determinant is evaluated exactly, so the sign is always right.
+
+const EPSILON: f64 = f64::EPSILON / 2.;
+const CCW_ERR_BOUND: f64 = (3. + 16. * EPSILON) * EPSILON;
+const O3D_ERR_BOUND: f64 = (7. + 56. * EPSILON) * EPSILON;
+const ICC_ERR_BOUND: f64 = (10. + 96. * EPSILON) * EPSILON;
+
+// Positive if `a`, `b` and `c` are in counter-clockwise order, negative if
+// clockwise and zero if collinear. The magnitude is twice the triangle area.
+pub fn orient2d(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    let det_left = (a[0] - c[0]) * (b[1] - c[1]);
+    let det_right = (a[1] - c[1]) * (b[0] - c[0]);
+    let det = det_left - det_right;
+
+    let det_sum = if det_left > 0. {
+        if det_right <= 0. {
+            return det;
+        }
+        det_left + det_right
+    } else if det_left < 0. {
+        if det_right >= 0. {
+            return det;
+        }
+        -det_left - det_right
+    } else {
+        return det;
+    };
+
+    if det.abs() >= CCW_ERR_BOUND * det_sum {
+        return det;
+    }
+
+    let acx = two_diff(a[0], c[0]);
+    let acy = two_diff(a[1], c[1]);
+    let bcx = two_diff(b[0], c[0]);
+    let bcy = two_diff(b[1], c[1]);
+
+    let left = expansion_product(&acx, &bcy);
+    let right = negate(&expansion_product(&acy, &bcx));
+    estimate(&expansion_sum(&left, &right))
+}
+
+// Whether two orientations are nonzero and of different signs, like the ends
+// of a segment strictly on both sides of a line
+pub fn opposite(a: f64, b: f64) -> bool {
+    (a > 0. && b < 0.) || (a < 0. && b > 0.)
+}
+
+// Whether `p`, known to be collinear with `a` and `b`, lies strictly between them
+pub fn strictly_between(a: [f64; 2], b: [f64; 2], p: [f64; 2]) -> bool {
+    let dot_a = (p[0] - a[0]) * (b[0] - a[0]) + (p[1] - a[1]) * (b[1] - a[1]);
+    let dot_b = (p[0] - b[0]) * (a[0] - b[0]) + (p[1] - b[1]) * (a[1] - b[1]);
+    dot_a > 0. && dot_b > 0.
+}
+
+// Positive if `d` is above the plane through `a`, `b` and `c`, seen in
+// counter-clockwise order from above, negative if below and zero if coplanar.
+// The magnitude is six times the tetrahedron volume.
+pub fn orient3d(a: [f64; 3], b: [f64; 3], c: [f64; 3], d: [f64; 3]) -> f64 {
+    // Triple product of the edges from `a`
+    let (ux, uy, uz) = (b[0] - a[0], b[1] - a[1], b[2] - a[2]);
+    let (vx, vy, vz) = (c[0] - a[0], c[1] - a[1], c[2] - a[2]);
+    let (wx, wy, wz) = (d[0] - a[0], d[1] - a[1], d[2] - a[2]);
+
+    let (vywz, vzwy) = (vy * wz, vz * wy);
+    let (vzwx, vxwz) = (vz * wx, vx * wz);
+    let (vxwy, vywx) = (vx * wy, vy * wx);
+
+    let det = ux * (vywz - vzwy) + uy * (vzwx - vxwz) + uz * (vxwy - vywx);
+    let permanent = (vywz.abs() + vzwy.abs()) * ux.abs()
+        + (vzwx.abs() + vxwz.abs()) * uy.abs()
+        + (vxwy.abs() + vywx.abs()) * uz.abs();
+
+    if det.abs() > O3D_ERR_BOUND * permanent {
+        return det;
+    }
+
+    let edge = |p: [f64; 3]| {
+        (
+            two_diff(p[0], a[0]),
+            two_diff(p[1], a[1]),
+            two_diff(p[2], a[2]),
+        )
+    };
+    let (ux, uy, uz) = edge(b);
+    let (vx, vy, vz) = edge(c);
+    let (wx, wy, wz) = edge(d);
+
+    let cross = |ux: &[f64], uy: &[f64], vx: &[f64], vy: &[f64]| {
+        expansion_sum(
+            &expansion_product(ux, vy),
+            &negate(&expansion_product(uy, vx)),
+        )
+    };
+
+    let x_term = expansion_product(&ux, &cross(&vy, &vz, &wy, &wz));
+    let y_term = expansion_product(&uy, &cross(&vz, &vx, &wz, &wx));
+    let z_term = expansion_product(&uz, &cross(&vx, &vy, &wx, &wy));
+
+    estimate(&expansion_sum(&expansion_sum(&x_term, &y_term), &z_term))
+}
+
+// Positive if `d` lies inside the circle through `a`, `b` and `c` (given in
+// counter-clockwise order), negative if outside and zero if on it
+pub fn incircle(a: [f64; 2], b: [f64; 2], c: [f64; 2], d: [f64; 2]) -> f64 {
+    let (adx, ady) = (a[0] - d[0], a[1] - d[1]);
+    let (bdx, bdy) = (b[0] - d[0], b[1] - d[1]);
+    let (cdx, cdy) = (c[0] - d[0], c[1] - d[1]);
+
+    let (bdxcdy, cdxbdy) = (bdx * cdy, cdx * bdy);
+    let alift = adx * adx + ady * ady;
+    let (cdxady, adxcdy) = (cdx * ady, adx * cdy);
+    let blift = bdx * bdx + bdy * bdy;
+    let (adxbdy, bdxady) = (adx * bdy, bdx * ady);
+    let clift = cdx * cdx + cdy * cdy;
+
+    let det = alift * (bdxcdy - cdxbdy) + blift * (cdxady - adxcdy) + clift * (adxbdy - bdxady);
+    let permanent = (bdxcdy.abs() + cdxbdy.abs()) * alift
+        + (cdxady.abs() + adxcdy.abs()) * blift
+        + (adxbdy.abs() + bdxady.abs()) * clift;
+
+    if det.abs() > ICC_ERR_BOUND * permanent {
+        return det;
+    }
+
+    let (adx, ady) = (two_diff(a[0], d[0]), two_diff(a[1], d[1]));
+    let (bdx, bdy) = (two_diff(b[0], d[0]), two_diff(b[1], d[1]));
+    let (cdx, cdy) = (two_diff(c[0], d[0]), two_diff(c[1], d[1]));
+
+    let lift = |dx: &[f64], dy: &[f64]| {
+        expansion_sum(&expansion_product(dx, dx), &expansion_product(dy, dy))
+    };
+    let cross = |ux: &[f64], uy: &[f64], vx: &[f64], vy: &[f64]| {
+        expansion_sum(
+            &expansion_product(ux, vy),
+            &negate(&expansion_product(uy, vx)),
+        )
+    };
+
+    let a_term = expansion_product(&lift(&adx, &ady), &cross(&bdx, &bdy, &cdx, &cdy));
+    let b_term = expansion_product(&lift(&bdx, &bdy), &cross(&cdx, &cdy, &adx, &ady));
+    let c_term = expansion_product(&lift(&cdx, &cdy), &cross(&adx, &ady, &bdx, &bdy));
+
+    estimate(&expansion_sum(&expansion_sum(&a_term, &b_term), &c_term))
+}
+
+// Expansions are sums of non-overlapping floats in increasing magnitude,
+// representing a value without rounding error
+
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let b_virtual = x - a;
+    let a_virtual = x - b_virtual;
+    (x, (a - a_virtual) + (b - b_virtual))
+}
+
+fn two_diff(a: f64, b: f64) -> Vec<f64> {
+    let (x, y) = two_sum(a, -b);
+    vec![y, x]
+}
+
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    (x, a.mul_add(b, -x))
+}
+
+fn negate(e: &[f64]) -> Vec<f64> {
+    e.iter().map(|x| -x).collect()
+}
+
+fn grow_expansion(e: &[f64], b: f64) -> Vec<f64> {
+    let mut h = Vec::with_capacity(e.len() + 1);
+    let mut q = b;
+    for &x in e {
+        let (sum, err) = two_sum(q, x);
+        q = sum;
+        if err != 0. {
+            h.push(err);
+        }
+    }
+
+    if q != 0. || h.is_empty() {
+        h.push(q);
+    }
+    h
+}
+
+fn expansion_sum(e: &[f64], f: &[f64]) -> Vec<f64> {
+    f.iter().fold(e.to_vec(), |sum, &x| grow_expansion(&sum, x))
+}
+
+fn scale_expansion(e: &[f64], b: f64) -> Vec<f64> {
+    let mut h = Vec::with_capacity(2 * e.len());
+    let mut q = 0.;
+    for &x in e {
+        let (product, product_err) = two_product(x, b);
+        let (sum, sum_err) = two_sum(q, product_err);
+        if sum_err != 0. {
+            h.push(sum_err);
+        }
+
+        let (next, next_err) = two_sum(product, sum);
+        if next_err != 0. {
+            h.push(next_err);
+        }
+        q = next;
+    }
+
+    if q != 0. || h.is_empty() {
+        h.push(q);
+    }
+    h
+}
+
+fn expansion_product(e: &[f64], f: &[f64]) -> Vec<f64> {
+    f.iter().fold(vec![0.], |sum, &x| {
+        expansion_sum(&sum, &scale_expansion(e, x))
+    })
+}
+
+fn estimate(e: &[f64]) -> f64 {
+    e.iter().sum()
+}
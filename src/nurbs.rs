@@ -0,0 +1,248 @@
+use crate::bezier::{project, to_homogeneous, ControlPoint, Homogeneous, RationalBezier};
+use crate::math::lerp;
+use crate::vertex::Vertex;
+
+fn to_vertex(point: Homogeneous) -> Vertex {
+    Vertex::from_coordinates(project(point))
+}
+
+// Index `k` of the knot span [knots[k], knots[k + 1]) holding `u`, kept
+// between `degree` and the last control point so the end of the domain
+// belongs to the last span
+fn find_span(degree: usize, knots: &[f64], count: usize, u: f64) -> usize {
+    let mut span = degree;
+    while span < count - 1 && knots[span + 1] <= u {
+        span += 1;
+    }
+    span
+}
+
+fn multiplicity(knots: &[f64], u: f64) -> usize {
+    knots.iter().filter(|&&k| k == u).count()
+}
+
+// Boehm's algorithm, inserts `u` once without changing the curve
+fn insert_knot(degree: usize, knots: &mut Vec<f64>, points: &mut Vec<Homogeneous>, u: f64) {
+    let p = degree;
+    let span = (0..knots.len() - 1)
+        .rev()
+        .find(|&k| knots[k] <= u && u < knots[k + 1])
+        .unwrap_or(points.len() - 1);
+    let s = multiplicity(knots, u);
+
+    let mut inserted = Vec::with_capacity(points.len() + 1);
+    for i in 0..=points.len() {
+        let point = if i + p <= span {
+            points[i]
+        } else if i + s > span {
+            points[i - 1]
+        } else {
+            let alpha = (u - knots[i]) / (knots[i + p] - knots[i]);
+            lerp(points[i - 1], points[i], alpha)
+        };
+        inserted.push(point);
+    }
+
+    knots.insert(span + 1, u);
+    *points = inserted;
+}
+
+// B-spline curve of any degree, rational when its weights aren't all the same
+// (a NURBS). Each knot span is a single polynomial piece that only depends on
+// `degree` + 1 control points, so moving a point only changes the curve
+// nearby. It's defined for parameters from knots[degree] to knots[n], with n
+// the number of control points, its domain.
+#[derive(Debug, Clone)]
+pub struct Nurbs {
+    degree: usize,
+    points: Vec<Vertex>,
+    weights: Vec<f32>,
+    knots: Vec<f32>,
+}
+
+impl Nurbs {
+    // The knots are non decreasing, and there are as many as points plus
+    // `degree` + 1. All the weights are one.
+    pub fn with_knots(degree: usize, points: &[Vertex], knots: &[f32]) -> Self {
+        assert!(degree >= 1, "Degree must be at least 1");
+        assert!(points.len() > degree, "Not enough points for the degree");
+        assert_eq!(
+            knots.len(),
+            points.len() + degree + 1,
+            "Wrong number of knots"
+        );
+        assert!(
+            knots.windows(2).all(|w| w[0] <= w[1]),
+            "Knots must not decrease"
+        );
+
+        Self {
+            degree,
+            points: Vec::from(points),
+            weights: vec![1.; points.len()],
+            knots: Vec::from(knots),
+        }
+    }
+
+    // Evenly spaced knots 0, 1, 2, ... The curve doesn't reach the first and
+    // last points.
+    pub fn uniform(degree: usize, points: &[Vertex]) -> Self {
+        let knots: Vec<f32> = (0..points.len() + degree + 1).map(|i| i as f32).collect();
+        Self::with_knots(degree, points, &knots)
+    }
+
+    // Knots repeated `degree` + 1 times at each end, with the rest evenly
+    // spaced in between, so the curve starts and ends at the first and last
+    // points like a Bezier curve. The domain is [0, 1].
+    pub fn clamped(degree: usize, points: &[Vertex]) -> Self {
+        let spans = points.len().saturating_sub(degree).max(1);
+        let knots: Vec<f32> = (0..points.len() + degree + 1)
+            .map(|i| (i.saturating_sub(degree).min(spans) as f32) / spans as f32)
+            .collect();
+        Self::with_knots(degree, points, &knots)
+    }
+
+    pub fn with_weights(self, weights: &[f32]) -> Self {
+        assert_eq!(self.points.len(), weights.len(), "One weight per point");
+        Self {
+            weights: Vec::from(weights),
+            ..self
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    pub fn points(&self) -> &[Vertex] {
+        &self.points
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    pub fn knots(&self) -> &[f32] {
+        &self.knots
+    }
+
+    pub fn domain(&self) -> (f32, f32) {
+        (self.knots[self.degree], self.knots[self.points.len()])
+    }
+
+    fn control_points(&self) -> Vec<Homogeneous> {
+        self.points
+            .iter()
+            .zip(&self.weights)
+            .map(|(p, &w)| to_homogeneous(p, w))
+            .collect()
+    }
+
+    fn knot_values(&self) -> Vec<f64> {
+        self.knots.iter().map(|&k| k as f64).collect()
+    }
+
+    fn from_parts(degree: usize, points: &[Homogeneous], knots: &[f64]) -> Self {
+        Self {
+            degree,
+            points: points.iter().copied().map(to_vertex).collect(),
+            weights: points.iter().map(|p| p[2] as f32).collect(),
+            knots: knots.iter().map(|&k| k as f32).collect(),
+        }
+    }
+
+    // De Boor's algorithm at `u`, clamped to the domain
+    pub fn single(&self, u: f32) -> Vertex {
+        let (start, end) = self.domain();
+        let u = u.clamp(start, end) as f64;
+
+        let p = self.degree;
+        let knots = self.knot_values();
+        let points = self.control_points();
+        let span = find_span(p, &knots, points.len(), u);
+
+        let mut d: Vec<Homogeneous> = points[span - p..=span].to_vec();
+        for r in 1..=p {
+            for j in (r..=p).rev() {
+                let i = j + span - p;
+                let alpha = (u - knots[i]) / (knots[i + p + 1 - r] - knots[i]);
+                d[j] = lerp(d[j - 1], d[j], alpha);
+            }
+        }
+
+        to_vertex(d[p])
+    }
+
+    // Evenly spaced samples over the whole domain, both ends included
+    pub fn samples(&self, steps: usize) -> Vec<Vertex> {
+        if steps == 0 {
+            return Vec::new();
+        }
+
+        let (start, end) = self.domain();
+        (0..=steps)
+            .map(|i| self.single(start + (end - start) * i as f32 / steps as f32))
+            .collect()
+    }
+
+    // Adds a knot at `u` and a control point, leaving the curve unchanged.
+    // Knots already repeated more than `degree` times are left alone.
+    pub fn insert_knot(&mut self, u: f32) {
+        let (start, end) = self.domain();
+        assert!(start <= u && u <= end, "Knot outside of the domain");
+
+        let mut knots = self.knot_values();
+        if multiplicity(&knots, u as f64) > self.degree {
+            return;
+        }
+
+        let mut points = self.control_points();
+        insert_knot(self.degree, &mut knots, &mut points, u as f64);
+        *self = Self::from_parts(self.degree, &points, &knots);
+    }
+
+    // One rational Bezier curve per non empty knot span of the domain, found
+    // by inserting every knot until it's repeated `degree` times
+    pub fn to_bezier_segments(&self) -> Vec<RationalBezier> {
+        let p = self.degree;
+        let mut knots = self.knot_values();
+        let mut points = self.control_points();
+        let (start, end) = (knots[p], knots[points.len()]);
+
+        let mut breaks: Vec<f64> = knots
+            .iter()
+            .copied()
+            .filter(|&k| start <= k && k <= end)
+            .collect();
+        breaks.dedup();
+
+        for &u in &breaks {
+            while multiplicity(&knots, u) < p {
+                insert_knot(p, &mut knots, &mut points, u);
+            }
+        }
+
+        breaks
+            .windows(2)
+            .map(|w| {
+                let span = find_span(p, &knots, points.len(), w[0]);
+                let segment = &points[span - p..=span];
+                let vertices: Vec<Vertex> = segment.iter().copied().map(to_vertex).collect();
+                let weights: Vec<f32> = segment.iter().map(|h| h[2] as f32).collect();
+                RationalBezier::new().with_weighted_points(&vertices, &weights)
+            })
+            .collect()
+    }
+
+    // The rational piece of every knot span flattened in turn, joined at the
+    // knots where consecutive pieces meet
+    pub fn flatten(&self, tolerance: f32) -> Vec<Vertex> {
+        let mut out = Vec::new();
+        for segment in self.to_bezier_segments() {
+            let points = segment.flatten(tolerance);
+            let skip = if out.is_empty() { 0 } else { 1 };
+            out.extend_from_slice(&points[skip..]);
+        }
+        out
+    }
+}
@@ -73,7 +73,7 @@ pub mod vertex {
         };
 
         frame
-            .draw(&buffer, &*INDICES, &program, &uniforms, &draw_params)
+            .draw(&buffer, *INDICES, &program, &uniforms, &draw_params)
             .unwrap();
     }
 
@@ -109,7 +109,7 @@ pub mod vertex {
         };
 
         frame
-            .draw(&buffer, &*INDICES, &program, &uniforms, &Default::default())
+            .draw(&buffer, *INDICES, &program, &uniforms, &Default::default())
             .unwrap();
     }
 }
@@ -118,7 +118,7 @@ pub mod points {
     use super::vertex::Vertex;
     use glium::Display;
 
-    #[derive(Debug, Clone)]
+    #[derive(Debug, Clone, PartialEq, Eq)]
     pub struct Point2d {
         pub x: i32,
         pub y: i32,
@@ -227,6 +227,14 @@ pub mod shapes {
             slf
         }
 
+        pub fn begin(&self) -> &Point2d {
+            &self.beg_point
+        }
+
+        pub fn end(&self) -> &Point2d {
+            &self.end_point
+        }
+
         pub fn slope(&self) -> f32 {
             let dy = (self.end_point.y - self.beg_point.y) as f32;
             let dx = (self.end_point.x - self.beg_point.x) as f32;
@@ -283,14 +291,12 @@ pub mod shapes {
                 (self.end_point.y - self.beg_point.y) / (self.end_point.x - self.beg_point.x);
 
             let func = |x: i32| self.beg_point.y + slope * (x - self.beg_point.x);
-            let steps = (self.end_point.x - self.beg_point.x).abs() as usize;
+            let steps = (self.end_point.x - self.beg_point.x).unsigned_abs() as usize;
 
             let mut buffer = Vec::with_capacity(steps);
-            let mut x = self.beg_point.x;
-            for _ in 0..=steps {
+            for x in (self.beg_point.x..).take(steps + 1) {
                 let y = func(x);
                 buffer.push(Point2d::new(x, y).to_vertex(display));
-                x += 1;
             }
 
             draw_vertex_as_points(&buffer, display, frame);
@@ -302,11 +308,12 @@ pub mod shapes {
                 (self.end_point.x, self.end_point.y),
             );
 
-            let slope = self.slope().abs();
+            // Compared exactly, a vertical line has no finite slope
+            let steep = (y1 - y0).abs() > (x1 - x0).abs();
 
             // Use axis 'y' as axis 'x', and viceversa
             // As if we were rotating the entire space
-            let ((x0, y0), (x1, y1)) = if slope > 1.0 {
+            let ((x0, y0), (x1, y1)) = if steep {
                 ((y0, x0), (y1, x1))
             } else {
                 ((x0, y0), (x1, y1))
@@ -324,7 +331,7 @@ pub mod shapes {
             let dx = x1 - x0;
             let dy = y1 - y0;
 
-            let steps = (x1 - x0).abs() as usize;
+            let steps = (x1 - x0).unsigned_abs() as usize;
             let mut buffer = Vec::with_capacity(steps);
 
             let mut x = x0;
@@ -337,7 +344,7 @@ pub mod shapes {
             while x <= x1 {
                 // If the space "was rotated", inverse the rotation and push
                 // to draw buffer
-                if slope > 1.0 {
+                if steep {
                     buffer.push(Point2d::new(y, x).to_vertex(display));
                 } else {
                     buffer.push(Point2d::new(x, y).to_vertex(display));
@@ -379,6 +386,10 @@ pub mod shapes {
             self.points.push(point.clone());
         }
 
+        pub fn points(&self) -> &[Point2d] {
+            &self.points
+        }
+
         pub fn rotate(&mut self, rotation_deg: f32) -> &mut Self {
             for point in &mut self.points {
                 *point = point.rotate(rotation_deg);
@@ -443,6 +454,38 @@ pub mod shapes {
         }
     }
 
+    // A polygon with an outer ring and any number of holes. Outer rings are
+    // counter-clockwise and holes clockwise.
+    #[derive(Debug, Clone)]
+    pub struct Polygon2d {
+        outer: Shape2d,
+        holes: Vec<Shape2d>,
+    }
+
+    impl Polygon2d {
+        pub fn new(outer: Shape2d, holes: Vec<Shape2d>) -> Self {
+            Self { outer, holes }
+        }
+
+        pub fn outer(&self) -> &Shape2d {
+            &self.outer
+        }
+
+        pub fn holes(&self) -> &[Shape2d] {
+            &self.holes
+        }
+
+        pub fn rings(&self) -> impl Iterator<Item = &Shape2d> {
+            std::iter::once(&self.outer).chain(self.holes.iter())
+        }
+
+        pub fn draw(&self, display: &Display, frame: &mut Frame, method: LineDrawMethod) {
+            for ring in self.rings() {
+                ring.draw(display, frame, method);
+            }
+        }
+    }
+
     #[derive(Debug, Clone)]
     pub struct Circle2d {
         origin: Point2d,
@@ -0,0 +1,412 @@
+use crate::bezier::{Bezier, ControlPoint};
+use crate::math::lerp;
+use crate::vertex::Vertex;
+
+type Point = [f64; 2];
+
+// How two consecutive segments meet at a joint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Continuity {
+    // Only the position is shared, the curve may have a corner
+    C0,
+    // Handles are collinear with the joint, with independent lengths
+    G1,
+    // Handles mirror each other, so the first derivative is continuous
+    C1,
+}
+
+// Chain of cubic Bezier segments. Control points are stored as the start of
+// the spline followed by two handles and an anchor per segment, so anchors
+// sit at indices multiple of 3.
+#[derive(Debug, Clone, Default)]
+pub struct Spline {
+    points: Vec<Vertex>,
+    joints: Vec<Continuity>,
+}
+
+impl Spline {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // C2 spline passing through all the points, with the curvature vanishing
+    // at both ends. The handles come from a tridiagonal system solved with
+    // the Thomas algorithm.
+    pub fn through_points(anchors: &[Vertex]) -> Self {
+        let mut spline = Self::new();
+        if anchors.len() < 3 {
+            for &anchor in anchors {
+                spline.push_point(anchor);
+            }
+            return spline;
+        }
+
+        let k: Vec<Point> = anchors.iter().map(Vertex::coordinates).collect();
+        let n = k.len() - 1;
+
+        // Rows a * x[i - 1] + b * x[i] + c * x[i + 1] = d for the first handles
+        let mut a = vec![1.; n];
+        let mut b = vec![4.; n];
+        let c = vec![1.; n];
+        let mut d: Vec<Point> = (0..n)
+            .map(|i| {
+                [
+                    4. * k[i][0] + 2. * k[i + 1][0],
+                    4. * k[i][1] + 2. * k[i + 1][1],
+                ]
+            })
+            .collect();
+
+        a[0] = 0.;
+        b[0] = 2.;
+        d[0] = [k[0][0] + 2. * k[1][0], k[0][1] + 2. * k[1][1]];
+        a[n - 1] = 2.;
+        b[n - 1] = 7.;
+        d[n - 1] = [8. * k[n - 1][0] + k[n][0], 8. * k[n - 1][1] + k[n][1]];
+
+        for i in 1..n {
+            let m = a[i] / b[i - 1];
+            b[i] -= m * c[i - 1];
+            d[i] = [d[i][0] - m * d[i - 1][0], d[i][1] - m * d[i - 1][1]];
+        }
+
+        let mut first = vec![[0.; 2]; n];
+        first[n - 1] = [d[n - 1][0] / b[n - 1], d[n - 1][1] / b[n - 1]];
+        for i in (0..n - 1).rev() {
+            first[i] = [
+                (d[i][0] - c[i] * first[i + 1][0]) / b[i],
+                (d[i][1] - c[i] * first[i + 1][1]) / b[i],
+            ];
+        }
+
+        spline.points.push(anchors[0]);
+        for i in 0..n {
+            let second = if i + 1 < n {
+                [
+                    2. * k[i + 1][0] - first[i + 1][0],
+                    2. * k[i + 1][1] - first[i + 1][1],
+                ]
+            } else {
+                [(k[n][0] + first[i][0]) / 2., (k[n][1] + first[i][1]) / 2.]
+            };
+
+            spline.points.push(Vertex::from_coordinates(first[i]));
+            spline.points.push(Vertex::from_coordinates(second));
+            spline.points.push(anchors[i + 1]);
+        }
+
+        spline.joints = vec![Continuity::C1; n - 1];
+        spline
+    }
+
+    // The first point starts the spline, every other one adds a segment to
+    // it with its handles at the thirds of the chord. The new joint is C1, so
+    // the outgoing handle follows the previous segment.
+    pub fn push_point(&mut self, anchor: Vertex) {
+        let end = match self.points.last() {
+            Some(end) => end.coordinates(),
+            None => {
+                self.points.push(anchor);
+                return;
+            }
+        };
+
+        let to = anchor.coordinates();
+        let third = |s: f64| Vertex::from_coordinates(lerp(end, to, s));
+        self.push_segment(third(1. / 3.), third(2. / 3.), anchor);
+
+        if !self.joints.is_empty() {
+            let joint = self.joints.len() - 1;
+            self.set_continuity(joint, Continuity::C1);
+        }
+    }
+
+    // Appends a segment with the given handles, joined with C0 continuity.
+    // Without a start point yet the first handle is used as one.
+    pub fn push_segment(&mut self, first: Vertex, second: Vertex, end: Vertex) {
+        if self.points.is_empty() {
+            self.points.push(first);
+        } else if self.points.len() > 1 {
+            self.joints.push(Continuity::C0);
+        }
+
+        self.points.extend_from_slice(&[first, second, end]);
+    }
+
+    pub fn points(&self) -> &[Vertex] {
+        &self.points
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.points.len().saturating_sub(1) / 3
+    }
+
+    pub fn segment(&self, index: usize) -> Bezier {
+        Bezier::new().with_points(&self.points[3 * index..3 * index + 4])
+    }
+
+    pub fn segments(&self) -> Vec<Bezier> {
+        (0..self.segment_count()).map(|i| self.segment(i)).collect()
+    }
+
+    // Joint `i` is the anchor between segments `i` and `i + 1`
+    pub fn continuity(&self, joint: usize) -> Continuity {
+        self.joints[joint]
+    }
+
+    // The handle leaving the joint is adjusted to match the incoming one
+    pub fn set_continuity(&mut self, joint: usize, continuity: Continuity) {
+        self.joints[joint] = continuity;
+        let anchor = 3 * (joint + 1);
+        self.enforce(joint, anchor - 1, anchor + 1);
+    }
+
+    // Moves a control point keeping every joint's continuity. Anchors drag
+    // their handles along, and handles next to a smooth joint turn the
+    // handle on the other side of it.
+    pub fn move_point(&mut self, index: usize, vertex: Vertex) {
+        let [x, y] = vertex.coordinates();
+        let [ox, oy] = self.points[index].coordinates();
+        let (dx, dy) = (x - ox, y - oy);
+        self.points[index] = vertex;
+
+        if index.is_multiple_of(3) {
+            for handle in [index.wrapping_sub(1), index + 1] {
+                if let Some(point) = self.points.get_mut(handle) {
+                    let [hx, hy] = point.coordinates();
+                    *point = Vertex::from_coordinates([hx + dx, hy + dy]);
+                }
+            }
+            return;
+        }
+
+        // The anchor this handle belongs to, and the handle across it
+        let (anchor, other) = if index % 3 == 1 {
+            (index - 1, index.wrapping_sub(2))
+        } else {
+            (index + 1, index + 2)
+        };
+
+        if anchor > 0 && anchor + 1 < self.points.len() {
+            self.enforce(anchor / 3 - 1, index, other);
+        }
+    }
+
+    // Moves handle `to` so that it matches handle `from` across the joint
+    fn enforce(&mut self, joint: usize, from: usize, to: usize) {
+        let anchor = self.points[3 * (joint + 1)].coordinates();
+        let reference = self.points[from].coordinates();
+        let adjusted = self.points[to].coordinates();
+        let (fx, fy) = (anchor[0] - reference[0], anchor[1] - reference[1]);
+
+        self.points[to] = match self.joints[joint] {
+            Continuity::C0 => return,
+            Continuity::C1 => Vertex::from_coordinates([anchor[0] + fx, anchor[1] + fy]),
+            Continuity::G1 => {
+                let from_length = fx.hypot(fy);
+                if from_length == 0. {
+                    return;
+                }
+
+                let mut length = (adjusted[0] - anchor[0]).hypot(adjusted[1] - anchor[1]);
+                if length == 0. {
+                    length = from_length;
+                }
+
+                let scale = length / from_length;
+                Vertex::from_coordinates([anchor[0] + fx * scale, anchor[1] + fy * scale])
+            }
+        };
+    }
+
+    // Point at `t` in [0, 1], with every segment taking an equal share of it.
+    // None without points.
+    pub fn single(&self, t: f32) -> Option<Vertex> {
+        let count = self.segment_count();
+        if count == 0 {
+            return self.points.first().copied();
+        }
+
+        let scaled = t.clamp(0., 1.) * count as f32;
+        let index = (scaled as usize).min(count - 1);
+        self.segment(index).single(scaled - index as f32)
+    }
+
+    // Every segment flattened in turn, sharing the anchors between them
+    pub fn flatten(&self, tolerance: f32) -> Vec<Vertex> {
+        let mut out: Vec<Vertex> = self.points.first().copied().into_iter().collect();
+        for segment in self.segments() {
+            out.extend_from_slice(&segment.flatten(tolerance)[1..]);
+        }
+        out
+    }
+}
+
+// Knot spacing of Catmull-Rom splines, the distance between consecutive
+// points raised to 0, 0.5 and 1. Centripetal spacing never forms cusps or
+// loops inside a segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parameterization {
+    Uniform,
+    Centripetal,
+    Chordal,
+}
+
+// Cubic Hermite spline, passing through every point with the given tangent
+// there. Segment `i` spans knots[i] to knots[i + 1], and the tangents are
+// derivatives with respect to that parameter.
+#[derive(Debug, Clone, Default)]
+pub struct Hermite {
+    points: Vec<Vertex>,
+    tangents: Vec<Vertex>,
+    knots: Vec<f32>,
+}
+
+impl Hermite {
+    // Knots are 0, 1, 2, ...
+    pub fn new(points: &[Vertex], tangents: &[Vertex]) -> Self {
+        assert_eq!(points.len(), tangents.len(), "One tangent per point");
+        Self {
+            points: Vec::from(points),
+            tangents: Vec::from(tangents),
+            knots: (0..points.len()).map(|i| i as f32).collect(),
+        }
+    }
+
+    pub fn with_knots(self, knots: &[f32]) -> Self {
+        assert_eq!(self.points.len(), knots.len(), "One knot per point");
+        assert!(knots.windows(2).all(|w| w[0] < w[1]), "Knots must increase");
+        Self {
+            knots: Vec::from(knots),
+            ..self
+        }
+    }
+
+    // Tangents from the neighbouring points, as in the Barry and Goldman
+    // formulation of non uniform Catmull-Rom splines. End tangents point
+    // straight to the next or previous point.
+    pub fn catmull_rom(points: &[Vertex], parameterization: Parameterization) -> Self {
+        let alpha = match parameterization {
+            Parameterization::Uniform => 0.,
+            Parameterization::Centripetal => 0.5,
+            Parameterization::Chordal => 1.,
+        };
+
+        let p: Vec<Point> = points.iter().map(Vertex::coordinates).collect();
+        let mut knots = vec![0.; p.len()];
+        for i in 1..p.len() {
+            // Repeated points still need increasing knots
+            let distance = (p[i][0] - p[i - 1][0]).hypot(p[i][1] - p[i - 1][1]);
+            let spacing = distance.powf(alpha);
+            knots[i] = knots[i - 1] + if spacing > 0. { spacing } else { 1. };
+        }
+
+        let slope = |i: usize, j: usize| {
+            let dt = knots[j] - knots[i];
+            [(p[j][0] - p[i][0]) / dt, (p[j][1] - p[i][1]) / dt]
+        };
+
+        let n = p.len();
+        let tangents: Vec<Vertex> = (0..n)
+            .map(|i| match i {
+                _ if n < 2 => [0., 0.],
+                0 => slope(0, 1),
+                _ if i == n - 1 => slope(n - 2, n - 1),
+                _ => {
+                    let (a, b, c) = (slope(i - 1, i), slope(i - 1, i + 1), slope(i, i + 1));
+                    [a[0] - b[0] + c[0], a[1] - b[1] + c[1]]
+                }
+            })
+            .map(Vertex::from_coordinates)
+            .collect();
+
+        let knots: Vec<f32> = knots.iter().map(|&k| k as f32).collect();
+        Self::new(points, &tangents).with_knots(&knots)
+    }
+
+    pub fn points(&self) -> &[Vertex] {
+        &self.points
+    }
+
+    pub fn tangents(&self) -> &[Vertex] {
+        &self.tangents
+    }
+
+    pub fn knots(&self) -> &[f32] {
+        &self.knots
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.points.len().saturating_sub(1)
+    }
+
+    // Segment `index` as a cubic Bezier curve, with its handles a third of
+    // the tangents away from the points, scaled by the segment's knot span
+    pub fn segment(&self, index: usize) -> Bezier {
+        let i = index + 1;
+        let (a, b) = (
+            self.points[i - 1].coordinates(),
+            self.points[i].coordinates(),
+        );
+        let (ta, tb) = (
+            self.tangents[i - 1].coordinates(),
+            self.tangents[i].coordinates(),
+        );
+        let scale = (self.knots[i] - self.knots[i - 1]) as f64 / 3.;
+
+        let points = [
+            a,
+            [a[0] + ta[0] * scale, a[1] + ta[1] * scale],
+            [b[0] - tb[0] * scale, b[1] - tb[1] * scale],
+            b,
+        ];
+        let points: Vec<Vertex> = points
+            .iter()
+            .copied()
+            .map(Vertex::from_coordinates)
+            .collect();
+        Bezier::new().with_points(&points)
+    }
+
+    pub fn to_beziers(&self) -> Vec<Bezier> {
+        (0..self.segment_count()).map(|i| self.segment(i)).collect()
+    }
+
+    // The same curve as a spline of cubic segments, with G1 joints since the
+    // handles on both sides of a point only match in direction
+    pub fn to_spline(&self) -> Spline {
+        let mut spline = Spline::new();
+        if let Some(&first) = self.points.first() {
+            spline.push_point(first);
+        }
+
+        for segment in self.to_beziers() {
+            let p = segment.points();
+            spline.push_segment(p[1], p[2], p[3]);
+        }
+
+        spline.joints = vec![Continuity::G1; spline.joints.len()];
+        spline
+    }
+
+    // Point at `t` in [0, 1], mapped linearly over the knots. Only the
+    // segment holding it is built. None without points.
+    pub fn single(&self, t: f32) -> Option<Vertex> {
+        let count = self.points.len();
+        if count < 2 {
+            return self.points.first().copied();
+        }
+
+        let (first, last) = (self.knots[0], self.knots[count - 1]);
+        let u = first + (last - first) * t.clamp(0., 1.);
+        let index = self.knots[1..count - 1].partition_point(|&k| k <= u);
+
+        let (a, b) = (self.knots[index], self.knots[index + 1]);
+        self.segment(index).single((u - a) / (b - a))
+    }
+
+    // Flattened as its spline of cubic segments, which is the same curve
+    pub fn flatten(&self, tolerance: f32) -> Vec<Vertex> {
+        self.to_spline().flatten(tolerance)
+    }
+}
@@ -0,0 +1,316 @@
+use crate::bezier::{Bezier, ControlPoint};
+use crate::math::{add, cross, dot, scale, sub};
+use crate::vertex::Vertex;
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+type Point = [f64; 2];
+
+// How the ends of an open path are closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cap {
+    // Cut straight at the end
+    Butt,
+    // Extended by half the width past the end
+    Square,
+    // Half a circle around the end
+    Round,
+}
+
+// How the outer side of a corner between two segments is filled, the inner
+// side is always covered by the segments themselves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Join {
+    // The outer edges extended until they meet, or a bevel if that's further
+    // than the miter limit
+    Miter,
+    // An arc around the corner
+    Round,
+    // A straight cut between the outer edges
+    Bevel,
+}
+
+// The direction rotated a quarter turn counter-clockwise, like
+// `Bezier::normal`
+fn normal([x, y]: Point) -> Point {
+    [-y, x]
+}
+
+fn to_bezier(points: &[Point]) -> Bezier {
+    let points: Vec<Vertex> = points
+        .iter()
+        .copied()
+        .map(Vertex::from_coordinates)
+        .collect();
+    Bezier::new().with_points(&points)
+}
+
+fn line(from: Point, to: Point) -> Bezier {
+    to_bezier(&[from, to])
+}
+
+// Arc around `center` starting at `from` and sweeping `sweep` radians, as
+// cubics of at most a quarter turn each. Their handles are 4 / 3 tan(θ / 4)
+// times the radius long, which strays less than 0.03% of the radius from the
+// circle.
+fn arc(center: Point, from: Point, sweep: f64, out: &mut Vec<Bezier>) {
+    let radius = sub(from, center);
+    let pieces = (sweep.abs() / FRAC_PI_2).ceil().max(1.) as usize;
+    let step = sweep / pieces as f64;
+    let handle = 4. / 3. * (step / 4.).tan();
+
+    let at = |angle: f64| {
+        let (sin, cos) = angle.sin_cos();
+        add(scale(radius, cos), scale(normal(radius), sin))
+    };
+
+    for i in 0..pieces {
+        let (a0, a1) = (step * i as f64, step * (i + 1) as f64);
+        let (r0, r1) = (at(a0), at(a1));
+        out.push(to_bezier(&[
+            add(center, r0),
+            add(center, add(r0, scale(normal(r0), handle))),
+            add(center, add(r1, scale(normal(r1), -handle))),
+            add(center, r1),
+        ]));
+    }
+}
+
+// Outline of a path of curves drawn with a given width, made of Bezier curves
+// that can be flattened and filled. Each side of the path is its offset at
+// half the width, the sides are connected at the corners with joins and at
+// the ends with caps. Where the offsets overlap themselves the outline winds
+// more than once, so it's meant to be filled with the nonzero rule.
+#[derive(Debug, Clone)]
+pub struct Stroke {
+    width: f32,
+    cap: Cap,
+    join: Join,
+    miter_limit: f32,
+    tolerance: f32,
+}
+
+impl Stroke {
+    // Butt caps and miter joins limited to 4 times the width, with the
+    // offsets within a quarter of a unit
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            cap: Cap::Butt,
+            join: Join::Miter,
+            miter_limit: 4.,
+            tolerance: 0.25,
+        }
+    }
+
+    pub fn with_cap(self, cap: Cap) -> Self {
+        Self { cap, ..self }
+    }
+
+    pub fn with_join(self, join: Join) -> Self {
+        Self { join, ..self }
+    }
+
+    // Longest miter, as a ratio to the width, before it's cut into a bevel
+    pub fn with_miter_limit(self, miter_limit: f32) -> Self {
+        Self {
+            miter_limit,
+            ..self
+        }
+    }
+
+    pub fn with_tolerance(self, tolerance: f32) -> Self {
+        Self { tolerance, ..self }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn cap(&self) -> Cap {
+        self.cap
+    }
+
+    pub fn join(&self) -> Join {
+        self.join
+    }
+
+    // Closed contours around the path, whose curves are joined end to end.
+    // An open path has a single contour going out along its left side and
+    // back along its right one. A closed path, ending where it starts, has
+    // one contour for each side and no caps.
+    pub fn outline(&self, path: &[Bezier]) -> Vec<Vec<Bezier>> {
+        // Curves collapsed into a point have no direction to offset along
+        let path: Vec<Bezier> = path
+            .iter()
+            .filter(|curve| {
+                let points = curve.points();
+                points.iter().any(|p| p.position != points[0].position)
+            })
+            .cloned()
+            .collect();
+        if path.is_empty() {
+            return Vec::new();
+        }
+
+        let reversed: Vec<Bezier> = path.iter().rev().map(|c| c.segment(1., 0.)).collect();
+        let first = path[0].points()[0];
+        let last = *path[path.len() - 1].points().last().unwrap();
+
+        if first.position == last.position {
+            let mut left = self.side(&path);
+            self.corner(&path[path.len() - 1], &path[0], &mut left);
+            let mut right = self.side(&reversed);
+            self.corner(&reversed[reversed.len() - 1], &reversed[0], &mut right);
+            return vec![left, right];
+        }
+
+        let mut contour = self.side(&path);
+        self.end_cap(&path[path.len() - 1], &mut contour);
+        contour.extend(self.side(&reversed));
+        self.end_cap(&reversed[reversed.len() - 1], &mut contour);
+        vec![contour]
+    }
+
+    // The left offset of every curve of the path, with the corners between
+    // them
+    fn side(&self, path: &[Bezier]) -> Vec<Bezier> {
+        let distance = self.width / 2.;
+        let mut out = Vec::new();
+        for (i, curve) in path.iter().enumerate() {
+            if i > 0 {
+                self.corner(&path[i - 1], curve, &mut out);
+            }
+            out.extend(curve.offset(distance, self.tolerance));
+        }
+        out
+    }
+
+    // Connects the left offsets of two consecutive curves. On the inner side
+    // of the turn they cross, and going through the corner itself keeps the
+    // overlap covered. On the outer side there's a gap that's filled with the
+    // join.
+    fn corner(&self, incoming: &Bezier, outgoing: &Bezier, out: &mut Vec<Bezier>) {
+        let distance = self.width as f64 / 2.;
+        let center = outgoing.points()[0].coordinates();
+        let t_in = incoming.tangent(1.).coordinates();
+        let t_out = outgoing.tangent(0.).coordinates();
+        let from = add(center, scale(normal(t_in), distance));
+        let to = add(center, scale(normal(t_out), distance));
+
+        let turn = cross(t_in, t_out);
+        let cos = dot(t_in, t_out).clamp(-1., 1.);
+        if turn.abs() < 1e-6 && cos > 0. {
+            if from != to {
+                out.push(line(from, to));
+            }
+            return;
+        }
+
+        // Turning back on itself the sign of the cross product is only
+        // rounding, and the join belongs ahead of the incoming curve. That's
+        // the outer side of a clockwise half turn from its left normal.
+        let reversal = turn.abs() < 1e-6;
+        if turn > 0. && !reversal {
+            out.push(line(from, center));
+            out.push(line(center, to));
+            return;
+        }
+
+        let sweep = if reversal { -PI } else { turn.atan2(cos) };
+        match self.join {
+            Join::Bevel => out.push(line(from, to)),
+
+            Join::Round => arc(center, from, sweep, out),
+
+            Join::Miter => {
+                // The tip is 1 / cos(θ / 2) half widths away for a turn of θ
+                let half_cos = ((1. + cos) / 2.).sqrt();
+                if half_cos > 0. && 1. / half_cos <= self.miter_limit as f64 {
+                    let bisector = add(normal(t_in), normal(t_out));
+                    let tip = add(center, scale(bisector, distance / (1. + cos)));
+                    out.push(line(from, tip));
+                    out.push(line(tip, to));
+                } else {
+                    out.push(line(from, to));
+                }
+            }
+        }
+    }
+
+    // Goes around the end of `curve` from its left side to its right one
+    fn end_cap(&self, curve: &Bezier, out: &mut Vec<Bezier>) {
+        let distance = self.width as f64 / 2.;
+        let center = curve.points().last().unwrap().coordinates();
+        let tangent = curve.tangent(1.).coordinates();
+        let side = scale(normal(tangent), distance);
+        let from = add(center, side);
+        let to = add(center, scale(side, -1.));
+
+        match self.cap {
+            Cap::Butt => out.push(line(from, to)),
+
+            Cap::Square => {
+                let ahead = scale(tangent, distance);
+                out.push(line(from, add(from, ahead)));
+                out.push(line(add(from, ahead), add(to, ahead)));
+                out.push(line(add(to, ahead), to));
+            }
+
+            Cap::Round => arc(center, from, -PI, out),
+        }
+    }
+}
+
+// Closed polyline around a contour, its curves flattened one after the other.
+// It ends back at its first point.
+pub fn flatten_contour(contour: &[Bezier], tolerance: f32) -> Vec<Vertex> {
+    let mut out = Vec::new();
+    for curve in contour {
+        let points = curve.flatten(tolerance);
+        let skip = if out.is_empty() { 0 } else { 1 };
+        out.extend_from_slice(&points[skip..]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // How far the flattened outline reaches along `direction`
+    fn reach(contours: &[Vec<Bezier>], direction: Point) -> f64 {
+        contours
+            .iter()
+            .flat_map(|contour| flatten_contour(contour, 0.01))
+            .map(|p| dot(p.coordinates(), direction))
+            .fold(f64::MIN, f64::max)
+    }
+
+    #[test]
+    fn round_join_on_reversal() {
+        // The tangents' cross product is +0 one way and -0 the other, the
+        // join has to go around the turning point both times
+        for &(start, end, ahead) in &[(0., 100., 1.), (100., 0., -1.)] {
+            let path = [line([start, 0.], [end, 0.]), line([end, 0.], [start, 0.])];
+            let contours = Stroke::new(20.).with_join(Join::Round).outline(&path);
+            let tip = ahead * end + 10.;
+            assert!((reach(&contours, [ahead, 0.]) - tip).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn round_join_on_reversed_curve() {
+        let points = [
+            Vertex::new(0., 0.),
+            Vertex::new(40., 30.),
+            Vertex::new(80., 0.),
+            Vertex::new(100., 0.),
+        ];
+        let curve = Bezier::new().with_points(&points);
+        let path = [curve.clone(), curve.segment(1., 0.)];
+
+        let contours = Stroke::new(20.).with_join(Join::Round).outline(&path);
+        assert!((reach(&contours, [1., 0.]) - 110.).abs() < 0.1);
+    }
+}
@@ -0,0 +1,389 @@
+use crate::bezier::{Bezier, ControlPoint};
+use crate::math::{cross3, norm, scale, sub};
+use crate::vertex::Vertex3;
+
+use std::collections::HashMap;
+
+type Point3 = [f64; 3];
+
+// A row or a column of the grid as a curve
+fn curve(points: &[Point3]) -> Bezier<Point3> {
+    Bezier::new().with_points(points)
+}
+
+fn unit(a: Point3) -> Point3 {
+    let length = norm(a);
+    if length == 0. {
+        a
+    } else {
+        scale(a, 1. / length)
+    }
+}
+
+// Samples of the surface of a control net over a grid of (`u_steps` + 1) x
+// (`v_steps` + 1) values, listed along `v` first. Each row is sampled as a
+// curve along `v`, then each column of those samples as a curve along `u`.
+fn sample_net(net: &[Point3], columns: usize, u_steps: usize, v_steps: usize) -> Vec<Point3> {
+    let rows: Vec<Vec<Point3>> = net
+        .chunks(columns)
+        .map(|row| curve(row).samples(v_steps).collect())
+        .collect();
+
+    let mut grid = vec![[0.; 3]; (u_steps + 1) * (v_steps + 1)];
+    for j in 0..=v_steps {
+        let column: Vec<Point3> = rows.iter().map(|row| row[j]).collect();
+        for (i, point) in curve(&column).samples(u_steps).enumerate() {
+            grid[i * (v_steps + 1) + j] = point;
+        }
+    }
+    grid
+}
+
+// Triangle mesh of a tessellated surface, with one normal per vertex and the
+// triangles wound counter-clockwise when seen from where the normals point
+#[derive(Debug, Clone, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex3>,
+    pub normals: Vec<Vertex3>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    // Adds the triangles of `other`, the vertices aren't shared between them
+    pub fn append(&mut self, other: Mesh) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend(other.vertices);
+        self.normals.extend(other.normals);
+        self.indices
+            .extend(other.indices.iter().map(|i| i + offset));
+    }
+}
+
+// Tensor product Bezier surface over a grid of control points, stored row by
+// row. Rows run along `u` and columns along `v`, so each row is a curve in `v`
+// and each column a curve in `u`. The patch goes through the four corners and
+// its edges are the Bezier curves of the outer rows and columns.
+#[derive(Debug, Clone)]
+pub struct BezierPatch {
+    rows: usize,
+    columns: usize,
+    points: Vec<Vertex3>,
+}
+
+impl BezierPatch {
+    pub fn new(rows: usize, columns: usize, points: &[Vertex3]) -> Self {
+        assert!(rows >= 2 && columns >= 2, "At least a 2x2 grid is needed");
+        assert_eq!(points.len(), rows * columns, "Wrong number of points");
+
+        Self {
+            rows,
+            columns,
+            points: Vec::from(points),
+        }
+    }
+
+    // Grid of 4x4 points, the most common kind of patch
+    pub fn bicubic(points: &[Vertex3]) -> Self {
+        Self::new(4, 4, points)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn points(&self) -> &[Vertex3] {
+        &self.points
+    }
+
+    pub fn point(&self, row: usize, column: usize) -> Vertex3 {
+        self.points[row * self.columns + column]
+    }
+
+    fn control_points(&self) -> Vec<Point3> {
+        self.points.iter().map(Vertex3::coordinates).collect()
+    }
+
+    // Evaluates each row as a curve at `v`, then the column of results at `u`.
+    // Nets always have a row and a column at least.
+    fn evaluate(net: &[Point3], columns: usize, u: f32, v: f32) -> Point3 {
+        let column: Vec<Point3> = net
+            .chunks(columns)
+            .map(|row| curve(row).single(v).unwrap())
+            .collect();
+        curve(&column).single(u).unwrap()
+    }
+
+    // Control net of the derivative along `u`, one row less
+    fn hodograph_u(&self) -> Vec<Point3> {
+        let net = self.control_points();
+        let n = (self.rows - 1) as f64;
+        (0..(self.rows - 1) * self.columns)
+            .map(|i| scale(sub(net[i + self.columns], net[i]), n))
+            .collect()
+    }
+
+    // Control net of the derivative along `v`, one column less
+    fn hodograph_v(&self) -> Vec<Point3> {
+        let n = (self.columns - 1) as f64;
+        self.control_points()
+            .chunks(self.columns)
+            .flat_map(|row| row.windows(2).map(move |w| scale(sub(w[1], w[0]), n)))
+            .collect()
+    }
+
+    pub fn single(&self, u: f32, v: f32) -> Vertex3 {
+        let point = Self::evaluate(&self.control_points(), self.columns, u, v);
+        Vertex3::from_coordinates(point)
+    }
+
+    // Partial derivative along `u` at (`u`, `v`)
+    pub fn derivative_u(&self, u: f32, v: f32) -> Vertex3 {
+        let point = Self::evaluate(&self.hodograph_u(), self.columns, u, v);
+        Vertex3::from_coordinates(point)
+    }
+
+    // Partial derivative along `v` at (`u`, `v`)
+    pub fn derivative_v(&self, u: f32, v: f32) -> Vertex3 {
+        let point = Self::evaluate(&self.hodograph_v(), self.columns - 1, u, v);
+        Vertex3::from_coordinates(point)
+    }
+
+    // Where a whole edge collapses into a single point, like at the top of a
+    // lid, one of the derivatives vanishes there. The normal is then the limit
+    // from inside the patch, taken a little towards its center.
+    fn normal_at(&self, du: &[Point3], dv: &[Point3], u: f32, v: f32) -> Point3 {
+        let columns = self.columns;
+        let normal = |u, v| {
+            cross3(
+                Self::evaluate(du, columns, u, v),
+                Self::evaluate(dv, columns - 1, u, v),
+            )
+        };
+
+        let mut n = normal(u, v);
+        let mut step = 1e-4;
+        while norm(n) < 1e-12 && step < 0.5 {
+            n = normal(u + (0.5 - u) * step, v + (0.5 - v) * step);
+            step *= 10.;
+        }
+        unit(n)
+    }
+
+    // Unit normal, the cross product of the derivatives along `u` and `v`.
+    // Zero if the patch is flattened into a curve or a point.
+    pub fn normal(&self, u: f32, v: f32) -> Vertex3 {
+        let (du, dv) = (self.hodograph_u(), self.hodograph_v());
+        Vertex3::from_coordinates(self.normal_at(&du, &dv, u, v))
+    }
+
+    // Steps along `u` and `v` for a grid within `tolerance` of the patch.
+    // The triangles stray from the surface by at most an eighth of its second
+    // derivative along their edges times their length squared. For a patch
+    // of degrees m and n those derivatives are bounded by the differences of
+    // the control points, m (m - 1) |P[i + 1][j] - 2 P[i][j] + P[i - 1][j]|
+    // along `u`, n (n - 1) times the same along `v`, and m n |P[i + 1][j + 1]
+    // - P[i + 1][j] - P[i][j + 1] + P[i][j]| for the twist between them.
+    pub fn steps(&self, tolerance: f32) -> (usize, usize) {
+        let net = self.control_points();
+        let (rows, columns) = (self.rows, self.columns);
+        let (m, n) = ((rows - 1) as f64, (columns - 1) as f64);
+        let tolerance = tolerance.max(f32::EPSILON) as f64;
+
+        let at = |i: usize, j: usize| net[i * columns + j];
+        let largest = |range_u: std::ops::Range<usize>,
+                       range_v: std::ops::Range<usize>,
+                       difference: &dyn Fn(usize, usize) -> Point3| {
+            range_u
+                .flat_map(|i| range_v.clone().map(move |j| (i, j)))
+                .map(|(i, j)| norm(difference(i, j)))
+                .fold(0., f64::max)
+        };
+
+        let bend_u = largest(1..rows - 1, 0..columns, &|i, j| {
+            sub(sub(at(i + 1, j), at(i, j)), sub(at(i, j), at(i - 1, j)))
+        });
+        let bend_v = largest(0..rows, 1..columns - 1, &|i, j| {
+            sub(sub(at(i, j + 1), at(i, j)), sub(at(i, j), at(i, j - 1)))
+        });
+        let twist = largest(0..rows - 1, 0..columns - 1, &|i, j| {
+            sub(
+                sub(at(i + 1, j + 1), at(i + 1, j)),
+                sub(at(i, j + 1), at(i, j)),
+            )
+        });
+
+        let (uu, vv) = (m * (m - 1.) / 8. * bend_u, n * (n - 1.) / 8. * bend_v);
+        let uv = m * n / 4. * twist;
+        let error = |ku: f64, kv: f64| uu / (ku * ku) + uv / (ku * kv) + vv / (kv * kv);
+
+        // Half the tolerance for each direction to start with, then more steps
+        // where the error is largest until the twist fits too
+        let start = |bound: f64| (2. * bound / tolerance).sqrt().ceil().max(1.);
+        let (mut ku, mut kv) = (start(uu), start(vv));
+        while error(ku, kv) > tolerance && ku.max(kv) < 256. {
+            let (eu, ev) = (uu / (ku * ku), vv / (kv * kv));
+            if eu > ev || (eu == ev && ku <= kv) {
+                ku += 1.;
+            } else {
+                kv += 1.;
+            }
+        }
+        ((ku as usize).min(256), (kv as usize).min(256))
+    }
+
+    // Grid of (`u_steps` + 1) x (`v_steps` + 1) samples, two triangles per
+    // cell
+    pub fn tessellate_grid(&self, u_steps: usize, v_steps: usize) -> Mesh {
+        let (u_steps, v_steps) = (u_steps.max(1), v_steps.max(1));
+        let (du, dv) = (self.hodograph_u(), self.hodograph_v());
+        let columns = self.columns;
+        let points = sample_net(&self.control_points(), columns, u_steps, v_steps);
+        let du_samples = sample_net(&du, columns, u_steps, v_steps);
+        let dv_samples = sample_net(&dv, columns - 1, u_steps, v_steps);
+
+        let mut mesh = Mesh {
+            vertices: points.into_iter().map(Vertex3::from_coordinates).collect(),
+            ..Default::default()
+        };
+        for i in 0..=u_steps {
+            for j in 0..=v_steps {
+                let k = i * (v_steps + 1) + j;
+                let mut normal = cross3(du_samples[k], dv_samples[k]);
+                if norm(normal) < 1e-12 {
+                    let (u, v) = (i as f32 / u_steps as f32, j as f32 / v_steps as f32);
+                    normal = self.normal_at(&du, &dv, u, v);
+                }
+                mesh.normals.push(Vertex3::from_coordinates(unit(normal)));
+            }
+        }
+
+        let index = |i: usize, j: usize| (i * (v_steps + 1) + j) as u32;
+        for i in 0..u_steps {
+            for j in 0..v_steps {
+                let (a, b) = (index(i, j), index(i + 1, j));
+                let (c, d) = (index(i + 1, j + 1), index(i, j + 1));
+                mesh.indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+        mesh
+    }
+
+    // Mesh within `tolerance` of the patch, with more triangles along the
+    // directions where it bends the most
+    pub fn tessellate(&self, tolerance: f32) -> Mesh {
+        let (u_steps, v_steps) = self.steps(tolerance);
+        self.tessellate_grid(u_steps, v_steps)
+    }
+}
+
+// Identifies the edge through `points` the same way in both directions, None
+// if it collapses into a single point, which has nothing to line up
+fn edge_key(points: &[Vertex3]) -> Option<Vec<[u32; 3]>> {
+    if points.iter().all(|p| p.position == points[0].position) {
+        return None;
+    }
+
+    // Adding zero turns -0 into 0, so both have the same bits
+    let forward: Vec<[u32; 3]> = points
+        .iter()
+        .map(|p| {
+            let [x, y, z] = p.position;
+            [(x + 0.).to_bits(), (y + 0.).to_bits(), (z + 0.).to_bits()]
+        })
+        .collect();
+    let mut backward = forward.clone();
+    backward.reverse();
+    Some(forward.min(backward))
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+// A single mesh for a model made of several patches, each within `tolerance`.
+// Neighbouring patches have to sample the edge they share at the same points
+// or cracks open between them, so steps are tied together across shared edges:
+// the steps of a patch along `u` are those of its two edges running along `u`,
+// and the same as the steps along any edge another patch shares with them.
+// Each group of tied steps takes the most any of its patches needs, so parts
+// of the model that aren't connected are refined on their own.
+pub fn tessellate_patches(patches: &[BezierPatch], tolerance: f32) -> Mesh {
+    // Steps along `u` of patch `p` are the group 2p, along `v` 2p + 1
+    let mut parent: Vec<usize> = (0..2 * patches.len()).collect();
+    let mut edges: HashMap<Vec<[u32; 3]>, usize> = HashMap::new();
+    for (p, patch) in patches.iter().enumerate() {
+        let (rows, columns) = (patch.rows, patch.columns);
+        let row = |i: usize| (0..columns).map(|j| patch.point(i, j)).collect::<Vec<_>>();
+        let column = |j: usize| (0..rows).map(|i| patch.point(i, j)).collect::<Vec<_>>();
+
+        // Rows are curves along `v`, columns along `u`
+        let sides = [
+            (row(0), 2 * p + 1),
+            (row(rows - 1), 2 * p + 1),
+            (column(0), 2 * p),
+            (column(columns - 1), 2 * p),
+        ];
+        for (points, group) in sides.iter() {
+            if let Some(key) = edge_key(points) {
+                let other = *edges.entry(key).or_insert(*group);
+                let (a, b) = (find(&mut parent, *group), find(&mut parent, other));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let steps: Vec<(usize, usize)> = patches.iter().map(|p| p.steps(tolerance)).collect();
+    let mut needed = vec![1; parent.len()];
+    for (p, &(u_steps, v_steps)) in steps.iter().enumerate() {
+        let (u, v) = (find(&mut parent, 2 * p), find(&mut parent, 2 * p + 1));
+        needed[u] = needed[u].max(u_steps);
+        needed[v] = needed[v].max(v_steps);
+    }
+
+    let mut mesh = Mesh::default();
+    for (p, patch) in patches.iter().enumerate() {
+        let (u, v) = (find(&mut parent, 2 * p), find(&mut parent, 2 * p + 1));
+        mesh.append(patch.tessellate_grid(needed[u], needed[v]));
+    }
+    mesh
+}
+
+// Reads patches in the BPT format of the Newell teaset and similar models: the
+// number of patches, then for each one its degrees along `u` and `v` followed
+// by the coordinates of its control points, row by row. Returns `None` if the
+// text doesn't follow it.
+pub fn parse_patches(source: &str) -> Option<Vec<BezierPatch>> {
+    let mut numbers = source.split_whitespace();
+    let total: usize = numbers.next()?.parse().ok()?;
+
+    // The counts aren't trusted, nothing is allocated up front from them and
+    // the text runs out before a count too large to be real is reached
+    let mut patches = Vec::new();
+    for _ in 0..total {
+        let u: usize = numbers.next()?.parse().ok()?;
+        let v: usize = numbers.next()?.parse().ok()?;
+        let rows = u.checked_add(1)?;
+        let columns = v.checked_add(1)?;
+        let count = rows.checked_mul(columns)?;
+        if rows < 2 || columns < 2 {
+            return None;
+        }
+
+        let mut points = Vec::new();
+        for _ in 0..count {
+            let mut coordinate = || numbers.next()?.parse::<f32>().ok();
+            points.push(Vertex3::new(coordinate()?, coordinate()?, coordinate()?));
+        }
+
+        patches.push(BezierPatch::new(rows, columns, &points));
+    }
+    Some(patches)
+}
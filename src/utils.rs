@@ -7,7 +7,7 @@ macro_rules! create_window {
         let wb = glutin::window::WindowBuilder::new()
             .with_title($title)
             .with_inner_size(LogicalSize::new($width, $height));
-        let cb = glutin::ContextBuilder::new();
+        let cb = glutin::ContextBuilder::new().with_depth_buffer(24);
         let display = glium::Display::new(wb, cb, &event_loop).expect("Failed to create display");
 
         (event_loop, display)
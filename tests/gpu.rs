@@ -0,0 +1,187 @@
+// Checks the curves evaluated in the vertex shader against the CPU without a
+// window, on an EGL surfaceless context like the one Mesa's llvmpipe software
+// driver provides:
+//
+//     LIBGL_ALWAYS_SOFTWARE=1 cargo test --test gpu
+//
+// Where EGL can't give an OpenGL 3.3 core context the test is skipped.
+
+use std::ffi::{c_void, CString};
+use std::os::raw::{c_char, c_int, c_uint};
+use std::ptr::null_mut;
+use std::rc::Rc;
+
+use glium::backend::{Backend, Context, Facade};
+use glium::SwapBuffersError;
+use libloading::Library;
+
+use toryn::bezier::Bezier;
+use toryn::gpu::{GpuBezier, MAX_POINTS};
+use toryn::vertex::Vertex;
+
+// Largest distance allowed between the shader's samples and the CPU ones.
+// The shader works in f32 and the CPU in f64, for curves a thousand pixels
+// across they differ by a few ten thousandths of a pixel.
+const TOLERANCE: f32 = 1e-3;
+
+const EGL_NONE: c_int = 0x3038;
+const EGL_OPENGL_API: c_uint = 0x30A2;
+const EGL_PLATFORM_SURFACELESS_MESA: c_uint = 0x31DD;
+const EGL_CONTEXT_MAJOR_VERSION: c_int = 0x3098;
+const EGL_CONTEXT_MINOR_VERSION: c_int = 0x30FB;
+const EGL_CONTEXT_OPENGL_PROFILE_MASK: c_int = 0x30FD;
+const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: c_int = 1;
+
+type Handle = *mut c_void;
+type GetPlatformDisplay = unsafe extern "C" fn(c_uint, Handle, *const isize) -> Handle;
+type Initialize = unsafe extern "C" fn(Handle, *mut c_int, *mut c_int) -> c_uint;
+type BindApi = unsafe extern "C" fn(c_uint) -> c_uint;
+type CreateContext = unsafe extern "C" fn(Handle, Handle, Handle, *const c_int) -> Handle;
+type MakeCurrent = unsafe extern "C" fn(Handle, Handle, Handle, Handle) -> c_uint;
+type GetProcAddress = unsafe extern "C" fn(*const c_char) -> *const c_void;
+
+// Context without any surface, everything is drawn to framebuffer objects
+struct Surfaceless {
+    display: Handle,
+    context: Handle,
+    make_current: MakeCurrent,
+    get_proc_address: GetProcAddress,
+    _library: Library,
+}
+
+impl Surfaceless {
+    fn new() -> Option<Self> {
+        let library = Library::new("libEGL.so.1").ok()?;
+        unsafe {
+            let get_platform_display = *library
+                .get::<GetPlatformDisplay>(b"eglGetPlatformDisplay\0")
+                .ok()?;
+            let initialize = *library.get::<Initialize>(b"eglInitialize\0").ok()?;
+            let bind_api = *library.get::<BindApi>(b"eglBindAPI\0").ok()?;
+            let create_context = *library.get::<CreateContext>(b"eglCreateContext\0").ok()?;
+            let make_current = *library.get::<MakeCurrent>(b"eglMakeCurrent\0").ok()?;
+            let get_proc_address = *library.get::<GetProcAddress>(b"eglGetProcAddress\0").ok()?;
+
+            let attributes = [EGL_NONE as isize];
+            let display = get_platform_display(
+                EGL_PLATFORM_SURFACELESS_MESA,
+                null_mut(),
+                attributes.as_ptr(),
+            );
+            let (mut major, mut minor) = (0, 0);
+            if display.is_null()
+                || initialize(display, &mut major, &mut minor) == 0
+                || bind_api(EGL_OPENGL_API) == 0
+            {
+                return None;
+            }
+
+            // No config is needed without a surface
+            let attributes = [
+                EGL_CONTEXT_MAJOR_VERSION,
+                3,
+                EGL_CONTEXT_MINOR_VERSION,
+                3,
+                EGL_CONTEXT_OPENGL_PROFILE_MASK,
+                EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                EGL_NONE,
+            ];
+            let context = create_context(display, null_mut(), null_mut(), attributes.as_ptr());
+            if context.is_null() || make_current(display, null_mut(), null_mut(), context) == 0 {
+                return None;
+            }
+
+            Some(Self {
+                display,
+                context,
+                make_current,
+                get_proc_address,
+                _library: library,
+            })
+        }
+    }
+}
+
+unsafe impl Backend for Surfaceless {
+    fn swap_buffers(&self) -> Result<(), SwapBuffersError> {
+        Ok(())
+    }
+
+    unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        let symbol = CString::new(symbol).unwrap();
+        (self.get_proc_address)(symbol.as_ptr())
+    }
+
+    fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+        (1, 1)
+    }
+
+    fn is_current(&self) -> bool {
+        true
+    }
+
+    unsafe fn make_current(&self) {
+        (self.make_current)(self.display, null_mut(), null_mut(), self.context);
+    }
+}
+
+struct Headless(Rc<Context>);
+
+impl Facade for Headless {
+    fn get_context(&self) -> &Rc<Context> {
+        &self.0
+    }
+}
+
+fn headless() -> Option<Headless> {
+    let backend = Surfaceless::new()?;
+    let context = unsafe { Context::new(backend, false, Default::default()) }.ok()?;
+    Some(Headless(context))
+}
+
+#[test]
+fn gpu_samples_match_interpolate() {
+    let facade = match headless() {
+        Some(facade) => facade,
+        None => {
+            eprintln!("Skipped, there's no EGL surfaceless OpenGL 3.3 context");
+            return;
+        }
+    };
+
+    // Points spread over a thousand pixels, for every degree the GPU takes
+    let points: Vec<Vertex> = (0..MAX_POINTS)
+        .map(|i| {
+            let a = i as f32;
+            Vertex::new(
+                500. + 450. * (0.7 * a).cos(),
+                500. + 450. * (0.91 * a).sin(),
+            )
+        })
+        .collect();
+
+    let mut gpu = GpuBezier::new(&facade, 256);
+    for count in 2..=MAX_POINTS {
+        let curve = Bezier::new()
+            .with_steps(gpu.steps())
+            .with_points(&points[..count]);
+        gpu.set_curve(&curve);
+
+        let expected = curve.interpolate().unwrap();
+        let actual = gpu.evaluate(&facade);
+        assert_eq!(actual.len(), expected.len());
+
+        for (i, (a, b)) in actual.iter().zip(&expected).enumerate() {
+            let [x0, y0] = a.position;
+            let [x1, y1] = b.position;
+            let distance = (x1 - x0).hypot(y1 - y0);
+            assert!(
+                distance <= TOLERANCE,
+                "Sample {} of {} points is {} pixels away",
+                i,
+                count,
+                distance
+            );
+        }
+    }
+}